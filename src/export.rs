@@ -0,0 +1,750 @@
+use anyhow::{bail, Context, Result};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use crate::import::METADATA_KEY;
+use crate::npz::NpzWriter;
+use crate::palp::LAYOUT_KEYS;
+use crate::parquet_utils::{ColumnKind, ColumnValues, ParquetTable};
+use crate::{ExportArgs, ExportFormat};
+
+pub fn run(args: ExportArgs) -> Result<()> {
+    let mut table = ParquetTable::open(&args.parquet_in)?;
+    if let Some(columns) = &args.columns {
+        table.select(columns)?;
+    }
+    let limit = args.limit.unwrap_or(usize::MAX);
+
+    let count = match args.format {
+        ExportFormat::Csv => write_csv(&table, b',', args.out, limit)?,
+        ExportFormat::Tsv => write_csv(&table, b'\t', args.out, limit)?,
+        ExportFormat::Jsonl => write_jsonl(&table, args.out, limit)?,
+        ExportFormat::Npz => write_npz_file(&table, args.out, limit)?,
+        ExportFormat::Sqlite => write_sqlite(&table, args.out, args.table, limit)?,
+    };
+
+    println!("rows written: {}", count);
+
+    Ok(())
+}
+
+/// Whether a column holds Hodge numbers, like `h11` or `h22`
+fn is_hodge_number(name: &str) -> bool {
+    name.len() == 3 && name.starts_with('h') && name[1..].chars().all(|x| x.is_ascii_digit())
+}
+
+/// Formats a value as text, with lists of lists written as `[[1,0],[0,1]]`, which is also valid
+/// JSON. Missing values give `None`.
+fn format_value(column: &ColumnValues, i: usize) -> Option<String> {
+    let value = match column {
+        ColumnValues::Int(values) => values[i].to_string(),
+        ColumnValues::Long(values) => values[i].to_string(),
+        ColumnValues::OptionalInt(values) => values[i]?.to_string(),
+        ColumnValues::IntListOfLists(_) | ColumnValues::OptionalIntListOfLists(_) => {
+            let lists: Vec<String> = column
+                .lists(i)?
+                .iter()
+                .map(|list| {
+                    let elements: Vec<String> = list.iter().map(|x| x.to_string()).collect();
+                    format!("[{}]", elements.join(","))
+                })
+                .collect();
+            format!("[{}]", lists.join(","))
+        }
+    };
+
+    Some(value)
+}
+
+fn write_csv<P: AsRef<Path>>(
+    table: &ParquetTable,
+    delimiter: u8,
+    path: P,
+    limit: usize,
+) -> Result<usize> {
+    let mut writer = csv::WriterBuilder::new()
+        .delimiter(delimiter)
+        .from_writer(BufWriter::new(File::create(path)?));
+
+    writer.write_record(table.names())?;
+
+    let mut count = 0;
+
+    for g in 0..table.num_row_groups() {
+        if count >= limit {
+            break;
+        }
+
+        let columns = table.read_row_group(g)?;
+        let row_count = columns.first().map_or(0, |c| c.len());
+
+        for i in 0..row_count.min(limit - count) {
+            writer.write_record(
+                columns
+                    .iter()
+                    .map(|c| format_value(c, i).unwrap_or_default()),
+            )?;
+            count += 1;
+        }
+    }
+
+    writer.flush()?;
+
+    Ok(count)
+}
+
+fn write_jsonl<P: AsRef<Path>>(table: &ParquetTable, path: P, limit: usize) -> Result<usize> {
+    let mut writer = BufWriter::new(File::create(path)?);
+
+    // the PALP text layout refers to all rows of the file and is left out
+    let metadata: serde_json::Map<String, serde_json::Value> = table
+        .key_value_metadata()
+        .into_iter()
+        .filter(|(key, _)| !LAYOUT_KEYS.contains(&key.as_str()))
+        .map(|(key, value)| (key, value.into()))
+        .collect();
+    writeln!(writer, "{}", serde_json::json!({ METADATA_KEY: metadata }))?;
+
+    let keys: Vec<String> = table
+        .names()
+        .iter()
+        .map(serde_json::to_string)
+        .collect::<Result<_, _>>()?;
+
+    let mut count = 0;
+
+    for g in 0..table.num_row_groups() {
+        if count >= limit {
+            break;
+        }
+
+        let columns = table.read_row_group(g)?;
+        let row_count = columns.first().map_or(0, |c| c.len());
+
+        for i in 0..row_count.min(limit - count) {
+            let fields: Vec<String> = keys
+                .iter()
+                .zip(&columns)
+                .map(|(key, c)| {
+                    let value = format_value(c, i).unwrap_or_else(|| "null".to_owned());
+                    format!("{}:{}", key, value)
+                })
+                .collect();
+            writeln!(writer, "{{{}}}", fields.join(","))?;
+            count += 1;
+        }
+    }
+
+    writer.flush()?;
+
+    Ok(count)
+}
+
+/// Calls `f` with column `c` of each row group and the number of its rows to write, until
+/// `count` rows are written.
+fn for_each_row_group(
+    table: &ParquetTable,
+    c: usize,
+    count: usize,
+    mut f: impl FnMut(&ColumnValues, usize) -> Result<()>,
+) -> Result<()> {
+    let mut remaining = count;
+
+    for g in 0..table.num_row_groups() {
+        if remaining == 0 {
+            break;
+        }
+
+        let column = table.read_column(g, c)?;
+        let take = column.len().min(remaining);
+        f(&column, take)?;
+        remaining -= take;
+    }
+
+    Ok(())
+}
+
+/// Writes the vertices of each polytope padded with zeros to the largest vertex count, the vertex
+/// counts, and the Hodge numbers, with -1 for missing Hodge numbers. The vertices are read twice,
+/// first for the shape of the array, and each array is streamed to the file.
+fn write_npz_file<P: AsRef<Path>>(table: &ParquetTable, path: P, limit: usize) -> Result<usize> {
+    let names = table.names();
+
+    let vertices_column = names
+        .iter()
+        .position(|x| x == "vertices")
+        .context("column vertices missing")?;
+    if table.kinds()[vertices_column] != ColumnKind::IntListOfLists {
+        bail!("column vertices is not a list of lists");
+    }
+    let hodge_number_columns: Vec<usize> = (0..names.len())
+        .filter(|&c| is_hodge_number(&names[c]) && !table.kinds()[c].is_list_of_lists())
+        .collect();
+
+    let row_count: usize = (0..table.num_row_groups())
+        .map(|g| table.row_group_len(g))
+        .sum();
+    let count = row_count.min(limit);
+
+    let mut max_vertex_count = 0;
+    let mut dimension = None;
+    for_each_row_group(table, vertices_column, count, |column, take| {
+        for i in 0..take {
+            let vertices = column.lists(i).expect("vertices");
+            max_vertex_count = max_vertex_count.max(vertices.len());
+
+            for vertex in vertices {
+                let dimension = *dimension.get_or_insert(vertex.len());
+                if vertex.len() != dimension {
+                    bail!(
+                        "vertex of dimension {}, expected {}",
+                        vertex.len(),
+                        dimension
+                    );
+                }
+            }
+        }
+        Ok(())
+    })?;
+    let dimension = dimension.unwrap_or(0);
+
+    let mut writer = NpzWriter::create(path)?;
+
+    writer.write_array("vertices", &[count, max_vertex_count, dimension], |out| {
+        let padding = vec![0; max_vertex_count * dimension];
+        for_each_row_group(table, vertices_column, count, |column, take| {
+            for i in 0..take {
+                let vertices = column.lists(i).expect("vertices");
+                for vertex in vertices {
+                    out.write(vertex)?;
+                }
+                out.write(&padding[vertices.len() * dimension..])?;
+            }
+            Ok(())
+        })
+    })?;
+
+    // the vertex counts are taken from the vertices unless they are stored
+    let vertex_count_column = names
+        .iter()
+        .position(|x| x == "vertex_count")
+        .unwrap_or(vertices_column);
+    let mut arrays = vec![("vertex_count", vertex_count_column)];
+    arrays.extend(hodge_number_columns.iter().map(|&c| (names[c].as_str(), c)));
+
+    for (name, c) in arrays {
+        writer.write_array(name, &[count], |out| {
+            for_each_row_group(table, c, count, |column, take| {
+                let values: Vec<i32> = match column {
+                    ColumnValues::Int(values) => values[..take].to_vec(),
+                    ColumnValues::OptionalInt(values) => {
+                        values[..take].iter().map(|x| x.unwrap_or(-1)).collect()
+                    }
+                    ColumnValues::IntListOfLists(values) => {
+                        values[..take].iter().map(|x| x.len() as i32).collect()
+                    }
+                    _ => bail!("column {} is not an integer column", names[c]),
+                };
+                out.write(&values)
+            })
+        })?;
+    }
+
+    writer.finish()?;
+
+    Ok(count)
+}
+
+/// Whether a file holds weight systems, which is told by the metadata since the weight columns
+/// might not be selected
+fn is_weight_systems(table: &ParquetTable) -> bool {
+    table.key_value_metadata().iter().any(|kv| kv.0 == "index")
+}
+
+/// The dimension of the polytopes of a file, which is one less than the number of weights for
+/// weight systems
+fn polytope_dimension(table: &ParquetTable) -> Result<usize> {
+    let names = table.names();
+    let is_weight_systems = is_weight_systems(table);
+
+    let dimension = match table
+        .key_value_metadata()
+        .iter()
+        .find(|kv| kv.0 == "dimension")
+    {
+        Some((_, value)) => value.parse().context("invalid dimension")?,
+        // files written before the dimension was stored have one column per Hodge number h1i
+        None if !is_weight_systems => names.iter().filter(|x| x.starts_with("h1")).count() + 2,
+        None => bail!("dimension missing"),
+    };
+
+    Ok(if is_weight_systems {
+        dimension - 1
+    } else {
+        dimension
+    })
+}
+
+/// The table name for the weight system category or for polytopes
+fn default_table_name(table: &ParquetTable) -> &'static str {
+    if !is_weight_systems(table) {
+        return "polytopes";
+    }
+
+    let metadata = table.key_value_metadata();
+    let is_true = |key: &str| metadata.iter().any(|kv| kv.0 == key && kv.1 == "true");
+
+    if !is_true("ip") {
+        "non_ip_weight_systems"
+    } else if !is_true("reflexive") {
+        "non_reflexive_weight_systems"
+    } else {
+        "reflexive_weight_systems"
+    }
+}
+
+/// Writes the rows to a new table of the database, with one child table `<table>_<column>` per
+/// list of lists column holding one row per element. The metadata is stored in the `metadata`
+/// table.
+fn write_sqlite<P: AsRef<Path>>(
+    table: &ParquetTable,
+    path: P,
+    table_name: Option<String>,
+    limit: usize,
+) -> Result<usize> {
+    use rusqlite::{params, params_from_iter, Connection};
+
+    let table_name = table_name.unwrap_or_else(|| default_table_name(table).to_owned());
+    let is_identifier = |name: &str| {
+        name.starts_with(|x: char| x.is_ascii_alphabetic() || x == '_')
+            && name.chars().all(|x| x.is_ascii_alphanumeric() || x == '_')
+    };
+    if !is_identifier(&table_name) {
+        bail!("invalid table name {}", table_name);
+    }
+
+    let names = table.names();
+    let kinds = table.kinds();
+    let dimension = polytope_dimension(table)?;
+
+    let scalar_columns: Vec<usize> = (0..names.len())
+        .filter(|&c| !kinds[c].is_list_of_lists())
+        .collect();
+    let nested_columns: Vec<usize> = (0..names.len())
+        .filter(|&c| kinds[c].is_list_of_lists())
+        .collect();
+
+    let mut connection = Connection::open(path)?;
+    let transaction = connection.transaction()?;
+
+    // a table of the same name is replaced, together with its child tables and metadata
+    let child_tables: Vec<String> = transaction
+        .prepare(
+            "SELECT m.name FROM sqlite_master AS m, pragma_foreign_key_list(m.name) AS f
+            WHERE m.type = 'table' AND f.\"table\" = ?1",
+        )?
+        .query_map([&table_name], |row| row.get(0))?
+        .collect::<rusqlite::Result<_>>()?;
+    for child_table in child_tables {
+        transaction.execute_batch(&format!("DROP TABLE \"{}\";", child_table))?;
+    }
+    transaction.execute_batch(&format!("DROP TABLE IF EXISTS \"{}\";", table_name))?;
+    let has_metadata: bool = transaction.query_row(
+        "SELECT count(*) > 0 FROM sqlite_master WHERE type = 'table' AND name = 'metadata'",
+        [],
+        |row| row.get(0),
+    )?;
+    if has_metadata {
+        transaction.execute("DELETE FROM metadata WHERE table_name = ?1", [&table_name])?;
+    }
+
+    let mut definitions = vec!["id INTEGER PRIMARY KEY".to_owned()];
+    for &c in &scalar_columns {
+        let constraint = if kinds[c] == ColumnKind::OptionalInt {
+            ""
+        } else {
+            " NOT NULL"
+        };
+        definitions.push(format!("\"{}\" INTEGER{}", names[c], constraint));
+    }
+    transaction.execute_batch(&format!(
+        "CREATE TABLE \"{}\" ({});",
+        table_name,
+        definitions.join(", ")
+    ))?;
+
+    let coordinate_names: Vec<String> = (0..dimension).map(|i| format!("x{}", i)).collect();
+    for &c in &nested_columns {
+        let mut definitions = vec![
+            format!("id INTEGER NOT NULL REFERENCES \"{}\" (id)", table_name),
+            "position INTEGER NOT NULL".to_owned(),
+        ];
+        definitions.extend(
+            coordinate_names
+                .iter()
+                .map(|x| format!("{} INTEGER NOT NULL", x)),
+        );
+        definitions.push("PRIMARY KEY (id, position)".to_owned());
+
+        transaction.execute_batch(&format!(
+            "CREATE TABLE \"{}_{}\" ({}) WITHOUT ROWID;",
+            table_name,
+            names[c],
+            definitions.join(", ")
+        ))?;
+    }
+
+    transaction.execute_batch(
+        "CREATE TABLE IF NOT EXISTS metadata (
+            table_name TEXT NOT NULL,
+            key TEXT NOT NULL,
+            value TEXT NOT NULL,
+            PRIMARY KEY (table_name, key)
+        );",
+    )?;
+    for (key, value) in table.key_value_metadata() {
+        transaction.execute(
+            "INSERT INTO metadata (table_name, key, value) VALUES (?1, ?2, ?3)",
+            params![table_name, key, value],
+        )?;
+    }
+
+    let mut count = 0;
+
+    {
+        let placeholders = vec!["?"; scalar_columns.len() + 1].join(", ");
+        let mut insert_row = transaction.prepare(&format!(
+            "INSERT INTO \"{}\" VALUES ({})",
+            table_name, placeholders
+        ))?;
+
+        let placeholders = vec!["?"; dimension + 2].join(", ");
+        let mut insert_elements = nested_columns
+            .iter()
+            .map(|&c| {
+                transaction.prepare(&format!(
+                    "INSERT INTO \"{}_{}\" VALUES ({})",
+                    table_name, names[c], placeholders
+                ))
+            })
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        for g in 0..table.num_row_groups() {
+            if count >= limit {
+                break;
+            }
+
+            let columns = table.read_row_group(g)?;
+            let row_count = columns.first().map_or(0, |c| c.len());
+
+            for i in 0..row_count.min(limit - count) {
+                let id = (count + 1) as i64;
+
+                let mut values = vec![Some(id)];
+                for &c in &scalar_columns {
+                    values.push(match &columns[c] {
+                        ColumnValues::Int(values) => Some(i64::from(values[i])),
+                        ColumnValues::Long(values) => Some(values[i]),
+                        ColumnValues::OptionalInt(values) => values[i].map(i64::from),
+                        ColumnValues::IntListOfLists(_)
+                        | ColumnValues::OptionalIntListOfLists(_) => unreachable!(),
+                    });
+                }
+                insert_row.execute(params_from_iter(values))?;
+
+                // null lists have no rows in the child table, like empty ones
+                for (insert, &c) in insert_elements.iter_mut().zip(&nested_columns) {
+                    let lists = columns[c].lists(i).unwrap_or_default();
+
+                    for (position, element) in lists.iter().enumerate() {
+                        if element.len() != dimension {
+                            bail!(
+                                "row {}: {} has an element of length {}",
+                                id,
+                                names[c],
+                                element.len()
+                            );
+                        }
+
+                        let mut values = vec![id, position as i64];
+                        values.extend(element.iter().map(|&x| i64::from(x)));
+                        insert.execute(params_from_iter(values))?;
+                    }
+                }
+
+                count += 1;
+            }
+        }
+    }
+
+    // the indexes are created after inserting, which is faster
+    let weights: Vec<String> = names
+        .iter()
+        .filter(|x| x.starts_with("weight"))
+        .map(|x| format!("\"{}\"", x))
+        .collect();
+    if !weights.is_empty() {
+        transaction.execute_batch(&format!(
+            "CREATE INDEX \"{0}_weights\" ON \"{0}\" ({1});",
+            table_name,
+            weights.join(", ")
+        ))?;
+    }
+    for &c in &scalar_columns {
+        if is_hodge_number(&names[c]) {
+            transaction.execute_batch(&format!(
+                "CREATE INDEX \"{0}_{1}\" ON \"{0}\" (\"{1}\");",
+                table_name, names[c]
+            ))?;
+        }
+    }
+
+    transaction.commit()?;
+
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parquet_utils::ParquetTableWriter;
+    use crate::{ImportArgs, ImportFormat};
+    use std::path::PathBuf;
+
+    fn temporary_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("{}-{}", std::process::id(), name))
+    }
+
+    /// Writes two 2d polytopes, the second without Hodge numbers, and opens them as a table.
+    fn polytopes(name: &str) -> ParquetTable {
+        let path = temporary_path(name);
+        let names = ["vertex_count", "h11", "vertices"].map(String::from);
+        let kinds = [
+            ColumnKind::Int,
+            ColumnKind::OptionalInt,
+            ColumnKind::IntListOfLists,
+        ];
+        let metadata = [("dimension".to_owned(), "2".to_owned())];
+
+        let mut writer =
+            ParquetTableWriter::new(File::create(&path).unwrap(), &names, &kinds, &metadata)
+                .unwrap();
+        writer
+            .write_row_group(&[
+                ColumnValues::Int(vec![3, 4]),
+                ColumnValues::OptionalInt(vec![Some(1), None]),
+                ColumnValues::IntListOfLists(vec![
+                    vec![vec![1, 0], vec![0, 1], vec![-1, -1]],
+                    vec![vec![1, 0], vec![0, 1], vec![-1, 0], vec![0, -1]],
+                ]),
+            ])
+            .unwrap();
+        writer.close().unwrap();
+
+        let table = ParquetTable::open(&path).unwrap();
+        std::fs::remove_file(path).unwrap();
+        table
+    }
+
+    /// Writes two 3d polytopes in the PALP schema, the second not reflexive, and opens them as a
+    /// table.
+    fn palp_polytopes(name: &str) -> ParquetTable {
+        let path = temporary_path(name);
+        let names = [
+            "vertices",
+            "vertex_count",
+            "facet_count",
+            "point_count",
+            "dual_point_count",
+            "h11",
+            "euler_characteristic",
+        ]
+        .map(String::from);
+        let mut kinds = [ColumnKind::Int; 7];
+        kinds[0] = ColumnKind::IntListOfLists;
+        kinds[4..].fill(ColumnKind::OptionalInt);
+
+        let octahedron = |x: i32| {
+            vec![
+                vec![x, 0, 0],
+                vec![0, x, 0],
+                vec![0, 0, x],
+                vec![-x, 0, 0],
+                vec![0, -x, 0],
+                vec![0, 0, -x],
+            ]
+        };
+
+        let mut writer =
+            ParquetTableWriter::new(File::create(&path).unwrap(), &names, &kinds, &[]).unwrap();
+        writer
+            .write_row_group(&[
+                ColumnValues::IntListOfLists(vec![octahedron(1), octahedron(2)]),
+                ColumnValues::Int(vec![6, 6]),
+                ColumnValues::Int(vec![8, 8]),
+                ColumnValues::Int(vec![7, 25]),
+                ColumnValues::OptionalInt(vec![Some(27), None]),
+                ColumnValues::OptionalInt(vec![Some(20), None]),
+                ColumnValues::OptionalInt(vec![Some(24), None]),
+            ])
+            .unwrap();
+        writer.close().unwrap();
+
+        let table = ParquetTable::open(&path).unwrap();
+        std::fs::remove_file(path).unwrap();
+        table
+    }
+
+    /// The values of all rows of a table as text.
+    fn rows(table: &ParquetTable) -> Vec<Vec<Option<String>>> {
+        let mut ret = Vec::new();
+        for g in 0..table.num_row_groups() {
+            let columns = table.read_row_group(g).unwrap();
+            for i in 0..table.row_group_len(g) {
+                ret.push(columns.iter().map(|c| format_value(c, i)).collect());
+            }
+        }
+        ret
+    }
+
+    /// Exports a table with `export`, imports the output in `format` again and returns the
+    /// imported table.
+    fn import_again(
+        export: impl FnOnce(&Path) -> Result<usize>,
+        format: ImportFormat,
+        name: &str,
+    ) -> ParquetTable {
+        let path = temporary_path(name);
+        let parquet_out = temporary_path(&format!("{}.parquet", name));
+        export(&path).unwrap();
+
+        crate::import::run(ImportArgs {
+            input: path.clone(),
+            format,
+            parquet_out: parquet_out.clone(),
+            index: None,
+        })
+        .unwrap();
+
+        let table = ParquetTable::open(&parquet_out).unwrap();
+        std::fs::remove_file(path).unwrap();
+        std::fs::remove_file(parquet_out).unwrap();
+        table
+    }
+
+    #[test]
+    fn jsonl_round_trip() {
+        let table = palp_polytopes("jsonl.parquet");
+        let path = temporary_path("polytopes.jsonl");
+
+        assert_eq!(write_jsonl(&table, &path, 1).unwrap(), 1);
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "\
+{\"_metadata\":{}}
+{\"vertices\":[[1,0,0],[0,1,0],[0,0,1],[-1,0,0],[0,-1,0],[0,0,-1]],\"vertex_count\":6,\"facet_count\":8,\"point_count\":7,\"dual_point_count\":27,\"h11\":20,\"euler_characteristic\":24}
+"
+        );
+        std::fs::remove_file(path).unwrap();
+
+        let imported = import_again(
+            |path| write_jsonl(&table, path, usize::MAX),
+            ImportFormat::Jsonl,
+            "round-trip.jsonl",
+        );
+        assert_eq!(imported.names(), table.names());
+        assert_eq!(imported.kinds(), table.kinds());
+        assert_eq!(rows(&imported), rows(&table));
+    }
+
+    #[test]
+    fn csv_round_trip() {
+        let table = palp_polytopes("csv-round-trip.parquet");
+
+        for (delimiter, format) in [(b',', ImportFormat::Csv), (b'\t', ImportFormat::Tsv)] {
+            let imported = import_again(
+                |path| write_csv(&table, delimiter, path, usize::MAX),
+                format,
+                "round-trip.csv",
+            );
+            assert_eq!(imported.names(), table.names());
+            assert_eq!(imported.kinds(), table.kinds());
+            assert_eq!(rows(&imported), rows(&table));
+        }
+    }
+
+    #[test]
+    fn sqlite_schema_and_export_again() {
+        let table = polytopes("sqlite.parquet");
+        let path = temporary_path("sqlite.db");
+
+        // the second export replaces the tables of the first
+        for _ in 0..2 {
+            assert_eq!(write_sqlite(&table, &path, None, usize::MAX).unwrap(), 2);
+        }
+
+        let connection = rusqlite::Connection::open(&path).unwrap();
+        let query = |sql: &str| -> Vec<String> {
+            let mut statement = connection.prepare(sql).unwrap();
+            let rows = statement.query_map([], |row| row.get(0)).unwrap();
+            rows.collect::<rusqlite::Result<_>>().unwrap()
+        };
+
+        assert_eq!(
+            query("SELECT name FROM sqlite_master WHERE type = 'table' ORDER BY name"),
+            ["metadata", "polytopes", "polytopes_vertices"]
+        );
+        assert_eq!(
+            query("SELECT name || ' ' || type || ' ' || \"notnull\" FROM pragma_table_info('polytopes')"),
+            ["id INTEGER 0", "vertex_count INTEGER 1", "h11 INTEGER 0"]
+        );
+        assert_eq!(
+            query("SELECT name FROM pragma_table_info('polytopes_vertices')"),
+            ["id", "position", "x0", "x1"]
+        );
+        assert_eq!(
+            query("SELECT name FROM sqlite_master WHERE type = 'index' AND sql IS NOT NULL"),
+            ["polytopes_h11"]
+        );
+        assert_eq!(
+            query("SELECT coalesce(CAST(h11 AS TEXT), 'null') FROM polytopes ORDER BY id"),
+            ["1", "null"]
+        );
+        assert_eq!(
+            query("SELECT id || ':' || x0 || ',' || x1 FROM polytopes_vertices WHERE position = 2"),
+            ["1:-1,-1", "2:-1,0"]
+        );
+        assert_eq!(
+            query("SELECT key || '=' || value FROM metadata"),
+            ["dimension=2"]
+        );
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn csv_and_tsv() {
+        let table = polytopes("csv.parquet");
+        let path = temporary_path("polytopes.csv");
+
+        // lists of lists are quoted in CSV and missing values are empty
+        assert_eq!(write_csv(&table, b',', &path, usize::MAX).unwrap(), 2);
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "\
+vertex_count,h11,vertices
+3,1,\"[[1,0],[0,1],[-1,-1]]\"
+4,,\"[[1,0],[0,1],[-1,0],[0,-1]]\"
+"
+        );
+
+        assert_eq!(write_csv(&table, b'\t', &path, 1).unwrap(), 1);
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "vertex_count\th11\tvertices\n3\t1\t[[1,0],[0,1],[-1,-1]]\n"
+        );
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
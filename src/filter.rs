@@ -0,0 +1,695 @@
+use anyhow::{bail, Context, Result};
+use std::fs::File;
+
+use crate::palp::{keeps_layout, HEADER_FORMATS_KEY, LAYOUT_KEYS};
+use crate::parquet_utils::{ColumnKind, ColumnValues, ParquetTable, ParquetTableWriter};
+use crate::FilterArgs;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Operator {
+    Or,
+    And,
+    Equal,
+    NotEqual,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Remainder,
+}
+
+impl Operator {
+    /// Binding strength, with the comparisons binding weaker than arithmetic as in Rust
+    fn precedence(self) -> u8 {
+        match self {
+            Operator::Or => 1,
+            Operator::And => 2,
+            Operator::Equal
+            | Operator::NotEqual
+            | Operator::Less
+            | Operator::LessEqual
+            | Operator::Greater
+            | Operator::GreaterEqual => 3,
+            Operator::Add | Operator::Subtract => 4,
+            Operator::Multiply | Operator::Divide | Operator::Remainder => 5,
+        }
+    }
+
+    fn is_comparison(self) -> bool {
+        self.precedence() == 3
+    }
+
+    fn is_logical(self) -> bool {
+        self.precedence() < 3
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+enum Token {
+    Number(i64),
+    Identifier(String),
+    Operator(Operator),
+    Not,
+    LeftParenthesis,
+    RightParenthesis,
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>> {
+    const OPERATORS: [(&str, Operator); 13] = [
+        ("||", Operator::Or),
+        ("&&", Operator::And),
+        ("==", Operator::Equal),
+        ("!=", Operator::NotEqual),
+        ("<=", Operator::LessEqual),
+        (">=", Operator::GreaterEqual),
+        ("<", Operator::Less),
+        (">", Operator::Greater),
+        ("+", Operator::Add),
+        ("-", Operator::Subtract),
+        ("*", Operator::Multiply),
+        ("/", Operator::Divide),
+        ("%", Operator::Remainder),
+    ];
+
+    let mut tokens = Vec::new();
+    let mut rest = input.trim_start();
+
+    while let Some(c) = rest.chars().next() {
+        let length = if c.is_ascii_digit() {
+            let length = rest
+                .find(|x: char| !x.is_ascii_digit())
+                .unwrap_or(rest.len());
+            let number = rest[..length]
+                .parse()
+                .with_context(|| format!("invalid number {}", &rest[..length]))?;
+            tokens.push(Token::Number(number));
+            length
+        } else if c.is_ascii_alphabetic() || c == '_' {
+            let length = rest
+                .find(|x: char| !x.is_ascii_alphanumeric() && x != '_')
+                .unwrap_or(rest.len());
+            tokens.push(Token::Identifier(rest[..length].to_owned()));
+            length
+        } else if let Some(&(text, operator)) = OPERATORS.iter().find(|x| rest.starts_with(x.0)) {
+            tokens.push(Token::Operator(operator));
+            text.len()
+        } else {
+            tokens.push(match c {
+                '!' => Token::Not,
+                '(' => Token::LeftParenthesis,
+                ')' => Token::RightParenthesis,
+                _ => bail!("unexpected character '{}'", c),
+            });
+            1
+        };
+
+        rest = rest[length..].trim_start();
+    }
+
+    Ok(tokens)
+}
+
+/// An expression over the integer columns of a row, where conditions are integers 0 and 1
+#[derive(Debug)]
+enum Expr {
+    Literal(i64),
+    Column(usize),
+    Negate(Box<Expr>),
+    Not(Box<Expr>),
+    Binary(Operator, Box<Expr>, Box<Expr>),
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum ExprType {
+    Int,
+    Bool,
+}
+
+struct Parser<'a> {
+    tokens: Vec<Token>,
+    position: usize,
+    names: &'a [String],
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    /// Parses operands and binary operators binding at least as strongly as `min_precedence`.
+    fn parse_binary(&mut self, min_precedence: u8) -> Result<Expr> {
+        let mut lhs = self.parse_unary()?;
+
+        while let Some(&Token::Operator(operator)) = self.peek() {
+            if operator.precedence() < min_precedence {
+                break;
+            }
+            self.position += 1;
+
+            let rhs = self.parse_binary(operator.precedence() + 1)?;
+
+            // comparisons cannot be chained
+            if operator.is_comparison() {
+                if let Some(&Token::Operator(next)) = self.peek() {
+                    if next.is_comparison() {
+                        bail!("comparisons cannot be chained, use parentheses");
+                    }
+                }
+            }
+
+            lhs = Expr::Binary(operator, Box::new(lhs), Box::new(rhs));
+        }
+
+        Ok(lhs)
+    }
+
+    fn parse_unary(&mut self) -> Result<Expr> {
+        match self.next() {
+            Some(Token::Number(x)) => Ok(Expr::Literal(x)),
+            Some(Token::Identifier(name)) => {
+                let c = self
+                    .names
+                    .iter()
+                    .position(|x| *x == name)
+                    .with_context(|| format!("unknown column {}", name))?;
+                Ok(Expr::Column(c))
+            }
+            Some(Token::Operator(Operator::Subtract)) => {
+                Ok(Expr::Negate(Box::new(self.parse_unary()?)))
+            }
+            Some(Token::Not) => Ok(Expr::Not(Box::new(self.parse_unary()?))),
+            Some(Token::LeftParenthesis) => {
+                let expr = self.parse_binary(0)?;
+                if self.next() != Some(Token::RightParenthesis) {
+                    bail!("')' expected");
+                }
+                Ok(expr)
+            }
+            Some(token) => bail!("unexpected {:?}", token),
+            None => bail!("unexpected end of expression"),
+        }
+    }
+}
+
+impl Expr {
+    fn parse(input: &str, names: &[String]) -> Result<Expr> {
+        let mut parser = Parser {
+            tokens: tokenize(input)?,
+            position: 0,
+            names,
+        };
+
+        let expr = parser.parse_binary(0)?;
+        if let Some(token) = parser.peek() {
+            bail!("unexpected {:?}", token);
+        }
+
+        Ok(expr)
+    }
+
+    /// Appends the columns used by the expression to `columns`.
+    fn collect_columns(&self, columns: &mut Vec<usize>) {
+        match self {
+            Expr::Literal(_) => {}
+            &Expr::Column(c) => columns.push(c),
+            Expr::Negate(expr) | Expr::Not(expr) => expr.collect_columns(columns),
+            Expr::Binary(_, lhs, rhs) => {
+                lhs.collect_columns(columns);
+                rhs.collect_columns(columns);
+            }
+        }
+    }
+
+    fn check_type(&self, names: &[String], kinds: &[ColumnKind]) -> Result<ExprType> {
+        let expect = |expr: &Expr, expected: ExprType| -> Result<()> {
+            if expr.check_type(names, kinds)? != expected {
+                let expected = match expected {
+                    ExprType::Int => "a number",
+                    ExprType::Bool => "a condition",
+                };
+                bail!("{} expected", expected);
+            }
+            Ok(())
+        };
+
+        match self {
+            Expr::Literal(_) => Ok(ExprType::Int),
+            &Expr::Column(c) => {
+                if kinds[c].is_list_of_lists() {
+                    bail!("column {} is not an integer column", names[c]);
+                }
+                Ok(ExprType::Int)
+            }
+            Expr::Negate(expr) => expect(expr, ExprType::Int).map(|_| ExprType::Int),
+            Expr::Not(expr) => expect(expr, ExprType::Bool).map(|_| ExprType::Bool),
+            Expr::Binary(operator, lhs, rhs) => {
+                let operand_type = if operator.is_logical() {
+                    ExprType::Bool
+                } else {
+                    ExprType::Int
+                };
+                expect(lhs, operand_type)?;
+                expect(rhs, operand_type)?;
+
+                Ok(if operator.is_logical() || operator.is_comparison() {
+                    ExprType::Bool
+                } else {
+                    ExprType::Int
+                })
+            }
+        }
+    }
+
+    /// Evaluates the expression for row `i`. Nulls propagate, except that `false && null` is
+    /// false and `true || null` is true, and so do overflows and divisions by zero.
+    fn evaluate(&self, columns: &[ColumnValues], i: usize) -> Option<i64> {
+        match self {
+            &Expr::Literal(x) => Some(x),
+            &Expr::Column(c) => match &columns[c] {
+                ColumnValues::Int(values) => Some(i64::from(values[i])),
+                ColumnValues::Long(values) => Some(values[i]),
+                ColumnValues::OptionalInt(values) => values[i].map(i64::from),
+                ColumnValues::IntListOfLists(_) | ColumnValues::OptionalIntListOfLists(_) => {
+                    unreachable!()
+                }
+            },
+            Expr::Negate(expr) => expr.evaluate(columns, i)?.checked_neg(),
+            Expr::Not(expr) => Some(1 - expr.evaluate(columns, i)?),
+            Expr::Binary(Operator::And, lhs, rhs) => {
+                match (lhs.evaluate(columns, i), rhs.evaluate(columns, i)) {
+                    (Some(0), _) | (_, Some(0)) => Some(0),
+                    (Some(_), Some(_)) => Some(1),
+                    _ => None,
+                }
+            }
+            Expr::Binary(Operator::Or, lhs, rhs) => {
+                match (lhs.evaluate(columns, i), rhs.evaluate(columns, i)) {
+                    (Some(1), _) | (_, Some(1)) => Some(1),
+                    (Some(_), Some(_)) => Some(0),
+                    _ => None,
+                }
+            }
+            Expr::Binary(operator, lhs, rhs) => {
+                let a = lhs.evaluate(columns, i)?;
+                let b = rhs.evaluate(columns, i)?;
+
+                match operator {
+                    Operator::Equal => Some(i64::from(a == b)),
+                    Operator::NotEqual => Some(i64::from(a != b)),
+                    Operator::Less => Some(i64::from(a < b)),
+                    Operator::LessEqual => Some(i64::from(a <= b)),
+                    Operator::Greater => Some(i64::from(a > b)),
+                    Operator::GreaterEqual => Some(i64::from(a >= b)),
+                    Operator::Add => a.checked_add(b),
+                    Operator::Subtract => a.checked_sub(b),
+                    Operator::Multiply => a.checked_mul(b),
+                    Operator::Divide => a.checked_div(b),
+                    Operator::Remainder => a.checked_rem(b),
+                    Operator::And | Operator::Or => unreachable!(),
+                }
+            }
+        }
+    }
+
+    /// Bounds the values of the expression over rows whose columns lie in `ranges`, ignoring
+    /// nulls. Conditions give the range of 0 and 1 they can take.
+    fn range(&self, ranges: &[Option<(i64, i64)>]) -> (i64, i64) {
+        const ANY: (i64, i64) = (i64::MIN, i64::MAX);
+
+        let clamp = |x: i128| x.clamp(i128::from(i64::MIN), i128::from(i64::MAX)) as i64;
+        let bounds = |values: [i128; 4]| {
+            let min = values.iter().min().expect("values");
+            let max = values.iter().max().expect("values");
+            (clamp(*min), clamp(*max))
+        };
+
+        match self {
+            &Expr::Literal(x) => (x, x),
+            &Expr::Column(c) => ranges[c].unwrap_or(ANY),
+            Expr::Negate(expr) => {
+                let (min, max) = expr.range(ranges);
+                (clamp(-i128::from(max)), clamp(-i128::from(min)))
+            }
+            Expr::Not(expr) => {
+                let (min, max) = expr.range(ranges);
+                (1 - max, 1 - min)
+            }
+            Expr::Binary(operator, lhs, rhs) => {
+                let (a_min, a_max) = lhs.range(ranges);
+                let (b_min, b_max) = rhs.range(ranges);
+
+                // conditions that can be false and that can be true
+                let condition = |can_be_false: bool, can_be_true: bool| {
+                    (i64::from(!can_be_false), i64::from(can_be_true))
+                };
+
+                let corners = |f: fn(i128, i128) -> i128| {
+                    let (a_min, a_max) = (i128::from(a_min), i128::from(a_max));
+                    let (b_min, b_max) = (i128::from(b_min), i128::from(b_max));
+                    bounds([
+                        f(a_min, b_min),
+                        f(a_min, b_max),
+                        f(a_max, b_min),
+                        f(a_max, b_max),
+                    ])
+                };
+
+                match operator {
+                    Operator::And => (a_min.min(b_min), a_max.min(b_max)),
+                    Operator::Or => (a_min.max(b_min), a_max.max(b_max)),
+                    Operator::Equal => condition(
+                        !(a_min == a_max && b_min == b_max && a_min == b_min),
+                        a_min <= b_max && b_min <= a_max,
+                    ),
+                    Operator::NotEqual => condition(
+                        a_min <= b_max && b_min <= a_max,
+                        !(a_min == a_max && b_min == b_max && a_min == b_min),
+                    ),
+                    Operator::Less => condition(a_max >= b_min, a_min < b_max),
+                    Operator::LessEqual => condition(a_max > b_min, a_min <= b_max),
+                    Operator::Greater => condition(a_min <= b_max, a_max > b_min),
+                    Operator::GreaterEqual => condition(a_min < b_max, a_max >= b_min),
+                    Operator::Add => (
+                        clamp(i128::from(a_min) + i128::from(b_min)),
+                        clamp(i128::from(a_max) + i128::from(b_max)),
+                    ),
+                    Operator::Subtract => (
+                        clamp(i128::from(a_min) - i128::from(b_max)),
+                        clamp(i128::from(a_max) - i128::from(b_min)),
+                    ),
+                    Operator::Multiply => corners(|a, b| a * b),
+                    // the quotient is monotonic in both operands if the divisor keeps its sign
+                    Operator::Divide if b_min > 0 || b_max < 0 => corners(|a, b| a / b),
+                    Operator::Divide | Operator::Remainder => ANY,
+                }
+            }
+        }
+    }
+}
+
+pub fn run(args: FilterArgs) -> Result<()> {
+    let mut table = ParquetTable::open(&args.input)?;
+    let input_names = table.names().to_vec();
+
+    let mut expr =
+        Expr::parse(&args.condition, table.names()).context("invalid --where expression")?;
+    if expr.check_type(table.names(), table.kinds())? != ExprType::Bool {
+        bail!("--where expression is not a condition");
+    }
+
+    // the selected columns are written, followed by the other columns of the condition
+    let output_count = match &args.columns {
+        Some(columns) => {
+            let mut used = Vec::new();
+            expr.collect_columns(&mut used);
+
+            let mut selected = columns.clone();
+            for c in used {
+                let name = &table.names()[c];
+                if !selected.contains(name) {
+                    selected.push(name.clone());
+                }
+            }
+
+            table.select(&selected)?;
+            expr = Expr::parse(&args.condition, table.names())?;
+
+            columns.len()
+        }
+        None => table.names().len(),
+    };
+
+    let names = table.names();
+
+    // the PALP text layout and the header formats describe all rows, and are added at the end if
+    // they still apply
+    let (layout, mut metadata): (Vec<_>, Vec<_>) = table
+        .key_value_metadata()
+        .into_iter()
+        .partition(|(key, _)| LAYOUT_KEYS.contains(&key.as_str()));
+    let header_formats = metadata
+        .iter()
+        .position(|(key, _)| key == HEADER_FORMATS_KEY)
+        .map(|i| metadata.remove(i));
+
+    let mut writer = ParquetTableWriter::new(
+        File::create(&args.output)?,
+        &names[..output_count],
+        &table.kinds()[..output_count],
+        &metadata,
+    )?;
+
+    let mut row_count = 0;
+    let mut match_count = 0;
+    let mut skipped_row_groups = 0;
+
+    for g in 0..table.num_row_groups() {
+        let ranges: Vec<_> = (0..names.len()).map(|c| table.column_range(g, c)).collect();
+
+        // no row of the group can match if the condition cannot be true
+        if expr.range(&ranges).1 == 0 {
+            skipped_row_groups += 1;
+            continue;
+        }
+
+        let columns = table.read_row_group(g)?;
+        let rows = columns.first().map_or(0, |c| c.len());
+
+        let keep: Vec<bool> = (0..rows)
+            .map(|i| expr.evaluate(&columns, i) == Some(1))
+            .collect();
+        let kept = keep.iter().filter(|&&x| x).count();
+
+        row_count += rows;
+        match_count += kept;
+
+        if kept > 0 {
+            let columns: Vec<ColumnValues> = columns[..output_count]
+                .iter()
+                .map(|c| c.filter(&keep))
+                .collect();
+            writer.write_row_group(&columns)?;
+        }
+    }
+
+    if match_count == row_count && skipped_row_groups == 0 {
+        if let Some((key, value)) = &header_formats {
+            writer.append_key_value_metadata(key, value);
+        }
+        if keeps_layout(&input_names, &names[..output_count]) {
+            for (key, value) in &layout {
+                writer.append_key_value_metadata(key, value);
+            }
+        }
+    }
+
+    writer.close()?;
+
+    println!("row groups skipped: {}", skipped_row_groups);
+    println!("rows read: {}", row_count);
+    println!("rows written: {}", match_count);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names() -> Vec<String> {
+        ["a", "b", "vertices"].map(String::from).to_vec()
+    }
+
+    fn kinds() -> [ColumnKind; 3] {
+        [
+            ColumnKind::OptionalInt,
+            ColumnKind::Int,
+            ColumnKind::IntListOfLists,
+        ]
+    }
+
+    /// Parses and type checks a condition.
+    fn parse(input: &str) -> Result<Expr> {
+        let expr = Expr::parse(input, &names())?;
+        if expr.check_type(&names(), &kinds())? != ExprType::Bool {
+            bail!("not a condition");
+        }
+        Ok(expr)
+    }
+
+    /// Evaluates a condition for the rows of `a` and `b`.
+    fn evaluate(input: &str, a: &[Option<i32>], b: &[i32]) -> Vec<Option<i64>> {
+        let expr = parse(input).unwrap();
+        let columns = [
+            ColumnValues::OptionalInt(a.to_vec()),
+            ColumnValues::Int(b.to_vec()),
+            ColumnValues::IntListOfLists(vec![Vec::new(); b.len()]),
+        ];
+        (0..b.len()).map(|i| expr.evaluate(&columns, i)).collect()
+    }
+
+    #[test]
+    fn parsed_expressions() {
+        assert_eq!(
+            evaluate("1 + 2 * 3 == 7 && !(4 < 3)", &[None], &[0]),
+            [Some(1)]
+        );
+        assert_eq!(
+            evaluate("b % 3 == 1 || -b >= -2", &[None; 4], &[1, 2, 3, 4]),
+            [Some(1), Some(1), Some(0), Some(1)]
+        );
+        assert_eq!(
+            evaluate("(b - 1) * 2 != b", &[None; 2], &[1, 2]),
+            [Some(1), Some(0)]
+        );
+
+        for (input, message) in [
+            (
+                "b < 1 < 2",
+                "comparisons cannot be chained, use parentheses",
+            ),
+            ("c > 1", "unknown column c"),
+            ("(b > 1", "')' expected"),
+            ("b >", "unexpected end of expression"),
+            ("b > 1 $", "unexpected character '$'"),
+            ("b > 1 2", "unexpected Number(2)"),
+            (
+                "b > 99999999999999999999",
+                "invalid number 99999999999999999999",
+            ),
+            ("b + (b > 1) > 0", "a number expected"),
+            ("b && b > 1", "a condition expected"),
+            ("vertices > 1", "column vertices is not an integer column"),
+            ("b + 1", "not a condition"),
+        ] {
+            let error = parse(input).err().unwrap();
+            assert_eq!(error.to_string(), message, "{}", input);
+        }
+    }
+
+    #[test]
+    fn nulls() {
+        let a = [None, Some(1), Some(0)];
+        let b = [0; 3];
+        assert_eq!(evaluate("a == 1", &a, &b), [None, Some(1), Some(0)]);
+        assert_eq!(evaluate("!(a == 1)", &a, &b), [None, Some(0), Some(1)]);
+        assert_eq!(evaluate("a == 1 || b == 0", &a, &b), [Some(1); 3]);
+        assert_eq!(evaluate("a == 1 && b == 1", &a, &b), [Some(0); 3]);
+        assert_eq!(
+            evaluate("a == 1 && b == 0", &a, &b),
+            [None, Some(1), Some(0)]
+        );
+
+        // divisions by zero and overflows are null as well
+        assert_eq!(evaluate("1 / a == 1", &a, &b), [None, Some(1), None]);
+        assert_eq!(
+            evaluate("(a + 9223372036854775807) > 0", &a, &b),
+            [None, None, Some(1)]
+        );
+    }
+
+    #[test]
+    fn row_group_ranges() {
+        let range =
+            |input: &str, ranges: &[Option<(i64, i64)>]| parse(input).unwrap().range(ranges);
+
+        let ranges = [Some((0, 5)), Some((2, 2)), None];
+        assert_eq!(range("a > 10", &ranges), (0, 0));
+        assert_eq!(range("a > 3", &ranges), (0, 1));
+        assert_eq!(range("a >= 0", &ranges), (1, 1));
+        assert_eq!(range("a * -2 > 0", &ranges), (0, 0));
+        assert_eq!(range("a / b == 3", &ranges), (0, 0));
+        assert_eq!(range("a % 2 == 1", &ranges), (0, 1));
+        assert_eq!(range("a > 10 || b == 2", &ranges), (1, 1));
+        assert_eq!(range("a > 10 && b == 2", &ranges), (0, 0));
+        assert_eq!(range("!(b == 2)", &ranges), (0, 0));
+
+        // columns without statistics can take any value
+        assert_eq!(range("a > 10", &[None, None, None]), (0, 1));
+    }
+
+    #[test]
+    fn filtered_files() {
+        let directory = std::env::temp_dir();
+        let path = |name: &str| directory.join(format!("{}-{}", std::process::id(), name));
+        let input = path("filter.parquet");
+
+        let names = ["vertex_count", "h11"].map(String::from);
+        let kinds = [ColumnKind::Int, ColumnKind::OptionalInt];
+        let mut metadata = vec![(HEADER_FORMATS_KEY.to_owned(), "plain".to_owned())];
+        metadata.extend(LAYOUT_KEYS.map(|key| (key.to_owned(), String::new())));
+
+        let mut writer =
+            ParquetTableWriter::new(File::create(&input).unwrap(), &names, &kinds, &metadata)
+                .unwrap();
+        for (vertex_counts, h11) in [(vec![4, 5], vec![Some(1), None]), (vec![6], vec![Some(3)])] {
+            writer
+                .write_row_group(&[
+                    ColumnValues::Int(vertex_counts),
+                    ColumnValues::OptionalInt(h11),
+                ])
+                .unwrap();
+        }
+        writer.close().unwrap();
+
+        let filter = |condition: &str, columns: Option<&[&str]>| -> ParquetTable {
+            let output = path("filtered.parquet");
+            run(FilterArgs {
+                condition: condition.to_owned(),
+                columns: columns.map(|x| x.iter().map(|&c| c.to_owned()).collect()),
+                input: input.clone(),
+                output: output.clone(),
+            })
+            .unwrap();
+            let table = ParquetTable::open(&output).unwrap();
+            std::fs::remove_file(output).unwrap();
+            table
+        };
+        let values = |table: &ParquetTable, c: usize| -> Vec<Option<i32>> {
+            let mut ret = Vec::new();
+            for g in 0..table.num_row_groups() {
+                match table.read_column(g, c).unwrap() {
+                    ColumnValues::Int(values) => ret.extend(values.into_iter().map(Some)),
+                    ColumnValues::OptionalInt(values) => ret.extend(values),
+                    _ => unreachable!(),
+                }
+            }
+            ret
+        };
+        let keys = |table: &ParquetTable| -> Vec<String> {
+            table
+                .key_value_metadata()
+                .into_iter()
+                .map(|(key, _)| key)
+                .collect()
+        };
+
+        // the second row group cannot match and is skipped, and rows with a null h11 do not match
+        let table = filter("vertex_count < 6 && h11 > 0", None);
+        assert_eq!(table.num_row_groups(), 1);
+        assert_eq!(values(&table, 0), [Some(4)]);
+        assert!(keys(&table).is_empty());
+
+        // the header formats and the layout are kept if all rows and the header columns are
+        let table = filter("vertex_count > 0", None);
+        assert_eq!(values(&table, 1), [Some(1), None, Some(3)]);
+        assert_eq!(keys(&table).len(), 1 + LAYOUT_KEYS.len());
+
+        // columns of the condition are read but not written
+        let table = filter("h11 == 3", Some(&["vertex_count"]));
+        assert_eq!(table.names(), ["vertex_count"]);
+        assert_eq!(values(&table, 0), [Some(6)]);
+
+        std::fs::remove_file(input).unwrap();
+    }
+}
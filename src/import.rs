@@ -0,0 +1,514 @@
+use anyhow::{bail, Context, Result};
+use std::fs;
+
+use crate::{ImportArgs, ImportFormat};
+
+/// The key of the line holding the metadata in JSON Lines files
+pub const METADATA_KEY: &str = "_metadata";
+
+/// A value of a record, as found in text formats
+pub enum Value {
+    Null,
+    Int(i64),
+    IntListOfLists(Vec<Vec<i64>>),
+}
+
+/// A column of records, typed by its first value that is not null
+enum Column {
+    /// a column without values so far, with the number of nulls
+    Null(usize),
+    Int(Vec<Option<i64>>),
+    /// the lists of lists back to back, with the number of inner lists of each record, or `None`
+    /// if it is null, and the length of the inner lists once one is seen
+    IntListOfLists {
+        data: Vec<i32>,
+        list_lengths: Vec<Option<i32>>,
+        element_length: Option<usize>,
+    },
+}
+
+impl Column {
+    fn len(&self) -> usize {
+        match self {
+            Column::Null(count) => *count,
+            Column::Int(values) => values.len(),
+            Column::IntListOfLists { list_lengths, .. } => list_lengths.len(),
+        }
+    }
+
+    fn push(&mut self, name: &str, value: Value) -> Result<()> {
+        if let (Column::Null(count), Value::Int(_)) = (&*self, &value) {
+            *self = Column::Int(vec![None; *count]);
+        }
+        if let (Column::Null(count), Value::IntListOfLists(_)) = (&*self, &value) {
+            *self = Column::IntListOfLists {
+                data: Vec::new(),
+                list_lengths: vec![None; *count],
+                element_length: None,
+            };
+        }
+
+        match (self, value) {
+            (Column::Null(count), Value::Null) => *count += 1,
+            (Column::Int(values), Value::Null) => values.push(None),
+            (Column::Int(values), Value::Int(x)) => values.push(Some(x)),
+            (Column::Int(_), Value::IntListOfLists(_)) => bail!("{} is not an integer", name),
+            (
+                Column::IntListOfLists {
+                    data,
+                    list_lengths,
+                    element_length,
+                },
+                value,
+            ) => {
+                let lists = match value {
+                    Value::Null => {
+                        list_lengths.push(None);
+                        return Ok(());
+                    }
+                    Value::IntListOfLists(lists) => lists,
+                    Value::Int(_) => bail!("{} is not a list of lists", name),
+                };
+
+                for list in &lists {
+                    let expected = *element_length.get_or_insert(list.len());
+                    if list.len() != expected {
+                        bail!(
+                            "{} has an element of length {}, expected {}",
+                            name,
+                            list.len(),
+                            expected
+                        );
+                    }
+
+                    for &x in list {
+                        data.push(
+                            i32::try_from(x).with_context(|| format!("{} out of range", name))?,
+                        );
+                    }
+                }
+                list_lengths.push(Some(lists.len() as i32));
+            }
+            (Column::Null(_), _) => unreachable!("typed above"),
+        }
+
+        Ok(())
+    }
+}
+
+/// Records read from a text format, stored by column
+pub struct Records {
+    names: Vec<String>,
+    columns: Vec<Column>,
+    /// the metadata given in the input, if any
+    metadata: Vec<(String, String)>,
+}
+
+impl Records {
+    fn new() -> Records {
+        Records {
+            names: Vec::new(),
+            columns: Vec::new(),
+            metadata: Vec::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.columns.first().map_or(0, Column::len)
+    }
+
+    pub fn has_column(&self, name: &str) -> bool {
+        self.names.iter().any(|x| x == name)
+    }
+
+    fn metadata(&self, key: &str) -> Option<&str> {
+        self.metadata
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Appends a record. Columns not seen before are only accepted for the first record, and
+    /// columns missing from a record are null.
+    fn push(&mut self, record: Vec<(String, Value)>) -> Result<()> {
+        let row_count = self.len();
+
+        for (name, value) in record {
+            let c = match self.names.iter().position(|x| *x == name) {
+                Some(c) => c,
+                None if row_count == 0 => {
+                    self.names.push(name);
+                    self.columns.push(Column::Null(0));
+                    self.columns.len() - 1
+                }
+                None => bail!("unknown column {}", name),
+            };
+
+            if self.columns[c].len() > row_count {
+                bail!("duplicate column {}", self.names[c]);
+            }
+            self.columns[c].push(&self.names[c], value)?;
+        }
+
+        for (column, name) in self.columns.iter_mut().zip(&self.names) {
+            if column.len() == row_count {
+                column.push(name, Value::Null)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn column(&self, name: &str) -> Result<&Column> {
+        let c = self
+            .names
+            .iter()
+            .position(|x| x == name)
+            .with_context(|| format!("column {} missing", name))?;
+        Ok(&self.columns[c])
+    }
+
+    pub fn optional_int_column(&self, name: &str) -> Result<Vec<Option<i32>>> {
+        match self.column(name)? {
+            &Column::Null(count) => Ok(vec![None; count]),
+            Column::Int(values) => values
+                .iter()
+                .enumerate()
+                .map(|(i, x)| {
+                    x.map(i32::try_from)
+                        .transpose()
+                        .with_context(|| format!("record {}: {} out of range", i + 1, name))
+                })
+                .collect(),
+            Column::IntListOfLists { .. } => bail!("{} is not an integer", name),
+        }
+    }
+
+    pub fn int_column(&self, name: &str) -> Result<Vec<i32>> {
+        self.optional_int_column(name)?
+            .into_iter()
+            .enumerate()
+            .map(|(i, x)| x.with_context(|| format!("record {}: {} missing", i + 1, name)))
+            .collect()
+    }
+
+    pub fn long_column(&self, name: &str) -> Result<Vec<i64>> {
+        let values = match self.column(name)? {
+            &Column::Null(count) => vec![None; count],
+            Column::Int(values) => values.clone(),
+            Column::IntListOfLists { .. } => bail!("{} is not an integer", name),
+        };
+
+        values
+            .into_iter()
+            .enumerate()
+            .map(|(i, x)| x.with_context(|| format!("record {}: {} missing", i + 1, name)))
+            .collect()
+    }
+
+    /// Returns the lists of lists of a column back to back, as expected by
+    /// `write_parquet_int_list_of_lists_column`, checking that each record has `list_lengths[i]`
+    /// inner lists of length `element_length`.
+    pub fn int_list_of_lists_column(
+        &self,
+        name: &str,
+        list_lengths: &[i32],
+        element_length: usize,
+    ) -> Result<Vec<i32>> {
+        let (data, lengths) = self.optional_int_list_of_lists_column(name, element_length)?;
+
+        for (i, (length, &expected)) in lengths.iter().zip(list_lengths).enumerate() {
+            match length {
+                None => bail!("record {}: {} missing", i + 1, name),
+                Some(length) if *length != expected => bail!(
+                    "record {}: {} has {} elements, expected {}",
+                    i + 1,
+                    name,
+                    length,
+                    expected
+                ),
+                _ => {}
+            }
+        }
+
+        Ok(data)
+    }
+
+    /// Returns the lists of lists of a column back to back, as expected by
+    /// `write_optional_parquet_int_list_of_lists_column`, together with the number of inner lists
+    /// of each record, or `None` if it is null, checking that the inner lists have length
+    /// `element_length`.
+    pub fn optional_int_list_of_lists_column(
+        &self,
+        name: &str,
+        element_length: usize,
+    ) -> Result<(Vec<i32>, Vec<Option<i32>>)> {
+        match self.column(name)? {
+            &Column::Null(count) => Ok((Vec::new(), vec![None; count])),
+            Column::Int(_) => bail!("{} is not a list of lists", name),
+            Column::IntListOfLists {
+                data,
+                list_lengths,
+                element_length: length,
+            } => {
+                if let Some(length) = *length {
+                    if length != element_length {
+                        bail!(
+                            "{} has elements of length {}, expected {}",
+                            name,
+                            length,
+                            element_length
+                        );
+                    }
+                }
+
+                Ok((data.clone(), list_lengths.clone()))
+            }
+        }
+    }
+
+    /// The length of the inner lists of a list of lists column, if there is one.
+    pub fn element_length(&self, name: &str) -> Result<Option<usize>> {
+        Ok(match self.column(name)? {
+            Column::IntListOfLists { element_length, .. } => *element_length,
+            _ => None,
+        })
+    }
+
+    /// Fails if there are columns other than `names`.
+    pub fn check_columns(&self, names: &[String]) -> Result<()> {
+        if let Some(name) = self.names.iter().find(|x| !names.contains(x)) {
+            bail!("unknown column {}", name);
+        }
+
+        Ok(())
+    }
+}
+
+pub fn run(args: ImportArgs) -> Result<()> {
+    let input = fs::read_to_string(&args.input)?;
+
+    let records = match args.format {
+        ImportFormat::Csv => parse_csv(&input, b',')?,
+        ImportFormat::Tsv => parse_csv(&input, b'\t')?,
+        ImportFormat::Jsonl => parse_jsonl(&input)?,
+    };
+
+    if records.len() == 0 {
+        bail!("no records");
+    }
+    println!("records: {}", records.len());
+
+    // weight system files are recognized by their weight columns
+    if records.has_column("weight0") {
+        let index = args
+            .index
+            .or_else(|| records.metadata("index").map(str::to_owned))
+            .context("weight systems require --index")?;
+        crate::ipws::write_records(&records, &index, args.parquet_out)
+    } else {
+        crate::palp::write_records(&records, args.parquet_out)
+    }
+}
+
+fn parse_json_value(value: &serde_json::Value) -> Option<Value> {
+    use serde_json::Value as Json;
+
+    match value {
+        Json::Null => Some(Value::Null),
+        Json::Number(x) => x.as_i64().map(Value::Int),
+        Json::Array(lists) => lists
+            .iter()
+            .map(|list| {
+                list.as_array()?
+                    .iter()
+                    .map(|x| x.as_i64())
+                    .collect::<Option<Vec<_>>>()
+            })
+            .collect::<Option<Vec<_>>>()
+            .map(Value::IntListOfLists),
+        _ => None,
+    }
+}
+
+/// Parses a value written by `export`: an integer, a list of lists as in JSON, or an empty field
+/// for a missing value.
+fn parse_text_value(text: &str) -> Option<Value> {
+    let text = text.trim();
+
+    if text.is_empty() {
+        Some(Value::Null)
+    } else if text.starts_with('[') {
+        parse_json_value(&serde_json::from_str(text).ok()?)
+    } else {
+        text.parse().ok().map(Value::Int)
+    }
+}
+
+fn parse_csv(input: &str, delimiter: u8) -> Result<Records> {
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(delimiter)
+        .from_reader(input.as_bytes());
+
+    let names: Vec<String> = reader
+        .headers()?
+        .iter()
+        .map(|x| x.trim().to_owned())
+        .collect();
+
+    let mut records = Records::new();
+
+    for (i, row) in reader.records().enumerate() {
+        // the header is line 1
+        let context = || format!("line {}", i + 2);
+
+        let row = row.with_context(context)?;
+
+        let record = names
+            .iter()
+            .zip(row.iter())
+            .map(|(name, text)| match parse_text_value(text) {
+                Some(value) => Ok((name.clone(), value)),
+                None => bail!("invalid value of {}: {}", name, text),
+            })
+            .collect::<Result<_>>()
+            .with_context(context)?;
+
+        records.push(record).with_context(context)?;
+    }
+
+    Ok(records)
+}
+
+fn parse_jsonl(input: &str) -> Result<Records> {
+    let mut records = Records::new();
+
+    for (i, line) in input.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let context = || format!("line {}", i + 1);
+
+        let mut object: serde_json::Map<String, serde_json::Value> =
+            serde_json::from_str(line).with_context(context)?;
+
+        // `export` writes the metadata of the Parquet file as the first line
+        if records.len() == 0 && object.len() == 1 {
+            if let Some(metadata) = object.remove(METADATA_KEY) {
+                records.metadata = parse_metadata(metadata).with_context(context)?;
+                continue;
+            }
+        }
+
+        let record = object
+            .into_iter()
+            .map(|(name, value)| match parse_json_value(&value) {
+                Some(value) => Ok((name, value)),
+                None => bail!("invalid value of {}", name),
+            })
+            .collect::<Result<_>>()
+            .with_context(context)?;
+
+        records.push(record).with_context(context)?;
+    }
+
+    Ok(records)
+}
+
+fn parse_metadata(metadata: serde_json::Value) -> Result<Vec<(String, String)>> {
+    let serde_json::Value::Object(metadata) = metadata else {
+        bail!("invalid {}", METADATA_KEY);
+    };
+
+    metadata
+        .into_iter()
+        .map(|(key, value)| match value {
+            serde_json::Value::String(value) => Ok((key, value)),
+            _ => bail!("invalid value of metadata {}", key),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn csv_records() {
+        let input = "\
+vertex_count, h11 ,vertices
+3,1,\"[[1,0],[0,1],[-1,-1]]\"
+4,,\"[[1,0],[0,1],[-1,0],[0,-1]]\"
+";
+        let records = parse_csv(input, b',').unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records.int_column("vertex_count").unwrap(), [3, 4]);
+        assert_eq!(records.optional_int_column("h11").unwrap(), [Some(1), None]);
+        assert_eq!(
+            records
+                .int_list_of_lists_column("vertices", &[3, 4], 2)
+                .unwrap()
+                .len(),
+            14
+        );
+
+        let records = parse_csv("weight0\tweight1\n1\t2\n", b'\t').unwrap();
+        assert_eq!(records.int_column("weight1").unwrap(), [2]);
+
+        for (input, message) in [
+            ("a,b\n1,2\n1,x\n", "line 3: invalid value of b: x"),
+            ("a\n1\n[[1]]\n", "line 3: a is not an integer"),
+        ] {
+            let error = parse_csv(input, b',').err().unwrap();
+            assert_eq!(format!("{:#}", error), message);
+        }
+
+        // weight systems need their index, which CSV files do not carry
+        let input = std::env::temp_dir().join(format!("{}-ws.csv", std::process::id()));
+        fs::write(&input, "weight0,weight1\n1,1\n").unwrap();
+        let error = run(ImportArgs {
+            input: input.clone(),
+            format: ImportFormat::Csv,
+            parquet_out: std::env::temp_dir().join(format!("{}-ws.parquet", std::process::id())),
+            index: None,
+        })
+        .unwrap_err();
+        assert_eq!(error.to_string(), "weight systems require --index");
+        fs::remove_file(input).unwrap();
+    }
+
+    #[test]
+    fn jsonl_records() {
+        let input = r#"{"_metadata":{"index":"1"}}
+{"weight0":1,"h11":null,"vertices":[[1,0],[0,1]]}
+
+{"weight0":2,"vertices":[[-1,-1]]}
+"#;
+        let records = parse_jsonl(input).unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records.metadata("index"), Some("1"));
+        assert_eq!(records.int_column("weight0").unwrap(), [1, 2]);
+        assert_eq!(records.optional_int_column("h11").unwrap(), [None, None]);
+        assert_eq!(records.element_length("vertices").unwrap(), Some(2));
+        assert_eq!(
+            records
+                .int_list_of_lists_column("vertices", &[2, 1], 2)
+                .unwrap(),
+            [1, 0, 0, 1, -1, -1]
+        );
+
+        for (input, message) in [
+            ("{\"a\":1}\n{\"b\":1}\n", "line 2: unknown column b"),
+            ("{\"a\":1}\n{\"a\":[[1]]}\n", "line 2: a is not an integer"),
+            ("{\"a\":\"x\"}\n", "line 1: invalid value of a"),
+            (
+                "{\"a\":1\n",
+                "line 1: EOF while parsing an object at line 1 column 6",
+            ),
+        ] {
+            let error = parse_jsonl(input).err().unwrap();
+            assert_eq!(format!("{:#}", error), message);
+        }
+    }
+}
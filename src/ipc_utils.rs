@@ -0,0 +1,189 @@
+use anyhow::Result;
+use arrow_array::builder::{Int32Builder, ListBuilder};
+use arrow_array::{ArrayRef, Int32Array, Int64Array, RecordBatch};
+use arrow_ipc::writer::FileWriter;
+use arrow_schema::{DataType, Field, Schema, SchemaRef};
+use std::collections::HashMap;
+use std::fs::File;
+use std::sync::Arc;
+
+use crate::parquet_utils::ColumnKind;
+
+/// The Arrow type of a list of lists column, with field names as in the Arrow schemas derived
+/// from Parquet files
+fn int_list_of_lists_type() -> DataType {
+    let inner = DataType::List(Arc::new(Field::new("element", DataType::Int32, false)));
+    DataType::List(Arc::new(Field::new("element", inner, false)))
+}
+
+fn build_field(name: &str, kind: ColumnKind) -> Field {
+    match kind {
+        ColumnKind::Int => Field::new(name, DataType::Int32, false),
+        ColumnKind::Long => Field::new(name, DataType::Int64, false),
+        ColumnKind::OptionalInt => Field::new(name, DataType::Int32, true),
+        ColumnKind::IntListOfLists => Field::new(name, int_list_of_lists_type(), false),
+        ColumnKind::OptionalIntListOfLists => Field::new(name, int_list_of_lists_type(), true),
+    }
+}
+
+pub fn build_int_array(data: &[i32]) -> ArrayRef {
+    Arc::new(Int32Array::from(data.to_vec()))
+}
+
+pub fn build_long_array(data: &[i64]) -> ArrayRef {
+    Arc::new(Int64Array::from(data.to_vec()))
+}
+
+pub fn build_optional_int_array(data: &[Option<i32>]) -> ArrayRef {
+    Arc::new(Int32Array::from(data.to_vec()))
+}
+
+/// Builds a list of lists array from the inner lists of length `element_length` back to back in
+/// `data`, and the number of inner lists of each row, `None` for null rows.
+pub fn build_int_list_of_lists_array(
+    data: &[i32],
+    list_lengths: impl Iterator<Item = Option<i32>>,
+    element_length: usize,
+) -> ArrayRef {
+    let DataType::List(outer_field) = int_list_of_lists_type() else {
+        unreachable!();
+    };
+    let DataType::List(inner_field) = outer_field.data_type().clone() else {
+        unreachable!();
+    };
+
+    let mut builder =
+        ListBuilder::new(ListBuilder::new(Int32Builder::new()).with_field(inner_field))
+            .with_field(outer_field);
+    let mut elements = data.chunks(element_length.max(1));
+
+    for length in list_lengths {
+        let Some(length) = length else {
+            builder.append(false);
+            continue;
+        };
+
+        for _ in 0..length {
+            if element_length > 0 {
+                let element = elements.next().expect("element");
+                builder.values().values().append_slice(element);
+            }
+            builder.values().append(true);
+        }
+        builder.append(true);
+    }
+
+    Arc::new(builder.finish())
+}
+
+/// Writes an Arrow IPC file with one record batch per row group of a table. The file is started
+/// with the first record batch, so that its schema has the metadata appended before.
+pub struct IpcWriter {
+    file: Option<File>,
+    writer: Option<(FileWriter<File>, SchemaRef)>,
+    fields: Vec<Field>,
+    metadata: HashMap<String, String>,
+}
+
+impl IpcWriter {
+    pub fn new(file: File, names: &[String], kinds: &[ColumnKind]) -> Self {
+        let fields = names
+            .iter()
+            .zip(kinds)
+            .map(|(name, &kind)| build_field(name, kind))
+            .collect();
+
+        Self {
+            file: Some(file),
+            writer: None,
+            fields,
+            metadata: HashMap::new(),
+        }
+    }
+
+    pub fn append_key_value_metadata(&mut self, key: &str, value: &str) {
+        assert!(
+            self.writer.is_none(),
+            "metadata after the first record batch"
+        );
+        self.metadata.insert(key.to_owned(), value.to_owned());
+    }
+
+    fn start(&mut self) -> Result<&mut (FileWriter<File>, SchemaRef)> {
+        if self.writer.is_none() {
+            let schema = Arc::new(Schema::new_with_metadata(
+                self.fields.clone(),
+                self.metadata.clone(),
+            ));
+            let file = self.file.take().expect("file");
+            self.writer = Some((FileWriter::try_new(file, &schema)?, schema));
+        }
+
+        Ok(self.writer.as_mut().expect("writer"))
+    }
+
+    pub fn write(&mut self, columns: Vec<ArrayRef>) -> Result<()> {
+        let (writer, schema) = self.start()?;
+        writer.write(&RecordBatch::try_new(schema.clone(), columns)?)?;
+
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> Result<()> {
+        let (writer, _) = self.start()?;
+        writer.finish()?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow_array::cast::AsArray;
+    use arrow_array::types::Int32Type;
+    use arrow_array::Array as _;
+    use arrow_ipc::reader::FileReader;
+
+    #[test]
+    fn list_of_lists_arrays() {
+        let array = build_int_list_of_lists_array(
+            &[1, 0, 0, 1, -1, -1],
+            [Some(2), None, Some(0), Some(1)].into_iter(),
+            2,
+        );
+        let array = array.as_list::<i32>();
+        assert_eq!(array.data_type(), &int_list_of_lists_type());
+        assert_eq!(array.len(), 4);
+        assert!(array.is_null(1));
+        assert_eq!(array.value(2).len(), 0);
+
+        let last = array.value(3);
+        let element = last.as_list::<i32>().value(0);
+        assert_eq!(element.as_primitive::<Int32Type>().values(), &[-1, -1]);
+
+        // inner lists can be empty
+        let array = build_int_list_of_lists_array(&[], [Some(2)].into_iter(), 0);
+        let row = array.as_list::<i32>().value(0);
+        assert_eq!(row.len(), 2);
+        assert_eq!(row.as_list::<i32>().value(1).len(), 0);
+    }
+
+    #[test]
+    fn ipc_file_without_record_batches() {
+        let path = std::env::temp_dir().join(format!("{}-empty.arrow", std::process::id()));
+
+        let names = ["vertex_count".to_owned()];
+        let mut writer = IpcWriter::new(File::create(&path).unwrap(), &names, &[ColumnKind::Int]);
+        writer.append_key_value_metadata("dimension", "4");
+        writer.finish().unwrap();
+
+        let reader = FileReader::try_new(File::open(&path).unwrap(), None).unwrap();
+        let schema = reader.schema();
+        assert_eq!(schema.field(0).name(), "vertex_count");
+        assert_eq!(schema.metadata()["dimension"], "4");
+        assert_eq!(reader.num_batches(), 0);
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
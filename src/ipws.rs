@@ -1,17 +1,25 @@
 use anyhow::{bail, Context, Result};
 use bytes::{Buf, BufMut};
 use parquet::file::metadata::KeyValue;
-use parquet::file::writer::SerializedFileWriter;
 use regex::Regex;
 use std::cmp::{min, Ordering};
+use std::collections::BTreeMap;
 use std::fs;
 use std::io::{Cursor, Write};
 use std::path::Path;
 use std::str::FromStr;
 use std::sync::Arc;
 
-use crate::parquet_utils::{build_parquet_int_field, write_parquet_int_column};
-use crate::IpwsArgs;
+use crate::import::Records;
+use crate::parquet_utils::{
+    build_parquet_int_field, build_parquet_int_list_of_lists_field, write_parquet_int_column,
+    write_parquet_int_list_of_lists_column, OutputWriter, ParquetOutput, RowGroupWriter,
+};
+use crate::polytope::{euler_characteristic, Polytope};
+use crate::{
+    IpwsArgs, IpwsCheckArgs, IpwsCheckHodgeArgs, IpwsClassifyArgs, IpwsCommands,
+    IpwsExportPalpArgs, IpwsImportPalpArgs, WeightSystemCategory,
+};
 
 #[derive(Default)]
 struct NonIpPolytopeInfo {
@@ -26,6 +34,7 @@ struct NonReflexivePolytopeInfo {
     vertex_count_list: Vec<i32>,
     facet_count_list: Vec<i32>,
     point_count_list: Vec<i32>,
+    coordinate_list: Option<Vec<i32>>,
 }
 
 #[derive(Default)]
@@ -38,6 +47,7 @@ struct ReflexivePolytopeInfo {
     dual_point_count_list: Vec<i32>,
     hodge_number_lists: Vec<Vec<i32>>,
     euler_characteristic_list: Vec<i32>,
+    coordinate_list: Option<Vec<i32>>,
 }
 
 impl NonIpPolytopeInfo {
@@ -89,12 +99,8 @@ impl ReflexivePolytopeInfo {
     }
 }
 
-fn euler_characteristic(h11: i32, h12: i32, h13: i32) -> i32 {
-    return 48 + 6 * (h11 - h12 + h13);
-}
-
-fn hodge_number_h22(h11: i32, h12: i32, h13: i32) -> i32 {
-    return 44 + 4 * h11 + 4 * h13 - 2 * h12;
+pub fn hodge_number_h22(h11: i32, h12: i32, h13: i32) -> i32 {
+    44 + 4 * h11 + 4 * h13 - 2 * h12
 }
 
 fn read_varint<T: Buf>(data: &mut T) -> u32 {
@@ -118,7 +124,7 @@ fn read_varint<T: Buf>(data: &mut T) -> u32 {
         pos += 7;
     }
 
-    return ret;
+    ret
 }
 
 fn write_varint<T: BufMut>(data: &mut T, mut value: u32) {
@@ -130,6 +136,14 @@ fn write_varint<T: BufMut>(data: &mut T, mut value: u32) {
     data.put_u8(value as u8);
 }
 
+fn format_index(numerator: u32, denominator: u32) -> String {
+    if denominator == 1 {
+        format!("{}", numerator)
+    } else {
+        format!("{}/{}", numerator, denominator)
+    }
+}
+
 fn read_weights<P: AsRef<Path>>(path: P, limit: usize) -> Result<(usize, String, Vec<i32>)> {
     let data = fs::read(path)?;
     let mut buf = Cursor::new(data);
@@ -139,11 +153,7 @@ fn read_weights<P: AsRef<Path>>(path: P, limit: usize) -> Result<(usize, String,
     let denominator = buf.get_u32();
     let ws_count = min(buf.get_u64() as usize, limit);
 
-    let index = if denominator == 1 {
-        format!("{}", numerator)
-    } else {
-        format!("{}/{}", numerator, denominator)
-    };
+    let index = format_index(numerator, denominator);
 
     println!("dimension: {}", dimension);
     println!("index: {}", index);
@@ -159,6 +169,19 @@ fn read_weights<P: AsRef<Path>>(path: P, limit: usize) -> Result<(usize, String,
     Ok((dimension, index, weights))
 }
 
+fn write_weights_header<T: BufMut>(
+    data: &mut T,
+    dimension: usize,
+    numerator: i32,
+    denominator: i32,
+    ws_count: usize,
+) {
+    data.put_u32(dimension as u32);
+    data.put_u32(numerator as u32);
+    data.put_u32(denominator as u32);
+    data.put_u64(ws_count as u64);
+}
+
 fn compare_weight_systems(a: &[i32], b: &[i32]) -> Ordering {
     let a_empty = a.is_empty();
     let b_empty = b.is_empty();
@@ -175,6 +198,7 @@ fn collect_weights_into(dest: &mut Vec<i32>, weight_lists: &[Vec<i32>], pos: usi
     dest.extend(weight_lists.iter().map(|wl| wl[pos]));
 }
 
+#[allow(clippy::too_many_arguments)]
 fn write_weights<P: AsRef<Path>>(
     dimension: usize,
     numerator: i32,
@@ -210,10 +234,13 @@ fn write_weights<P: AsRef<Path>>(
     let mut ws_buf = Vec::new();
     let mut pi_buf = Vec::new();
 
-    ws_buf.put_u32(dimension as u32);
-    ws_buf.put_u32(numerator as u32);
-    ws_buf.put_u32(denominator as u32);
-    ws_buf.put_u64((non_ip_count + non_reflexive_count + reflexive_count) as u64);
+    write_weights_header(
+        &mut ws_buf,
+        dimension,
+        numerator,
+        denominator,
+        non_ip_count + non_reflexive_count + reflexive_count,
+    );
 
     if non_ip_count > 0 {
         collect_weights_into(&mut non_ip_weights, non_ip_wl, 0);
@@ -332,7 +359,7 @@ fn write_weights<P: AsRef<Path>>(
     Ok(())
 }
 
-fn append_weight_system(weight_lists: &mut Vec<Vec<i32>>, weight_system: &[i32]) {
+fn append_weight_system(weight_lists: &mut [Vec<i32>], weight_system: &[i32]) {
     for (i, &w) in weight_system.iter().enumerate() {
         weight_lists[i].push(w);
     }
@@ -403,9 +430,10 @@ fn read_polytope_info<P: AsRef<Path>>(
                 let h13 = reflexive.hodge_number_lists[2][i];
 
                 reflexive.hodge_number_lists[3].push(hodge_number_h22(h11, h12, h13));
+                let euler = euler_characteristic(&[h11, h12, h13].map(i64::from));
                 reflexive
                     .euler_characteristic_list
-                    .push(euler_characteristic(h11, h12, h13));
+                    .push(euler.expect("three Hodge numbers") as i32);
             }
 
             continue;
@@ -417,8 +445,843 @@ fn read_polytope_info<P: AsRef<Path>>(
     Ok((non_ip, non_reflexive, reflexive))
 }
 
+/// Computes the vertices of the polytope of each weight system, checking them against the vertex
+/// counts.
+fn compute_vertices(
+    weight_lists: &[Vec<i32>],
+    vertex_count_list: &[i32],
+    index: &str,
+) -> Result<Vec<i32>> {
+    if index != "1" {
+        bail!("vertices can only be computed for weight systems of index 1");
+    }
+
+    let mut coordinates = Vec::new();
+
+    for (i, &vertex_count) in vertex_count_list.iter().enumerate() {
+        let weights: Vec<i64> = weight_lists.iter().map(|wl| wl[i].into()).collect();
+        let polytope = Polytope::from_weights(&weights)
+            .with_context(|| format!("weight system {:?}", weights))?;
+
+        if polytope.vertices.len() != vertex_count as usize {
+            bail!(
+                "weight system {:?}: vertex count expected {}, computed {}",
+                weights,
+                vertex_count,
+                polytope.vertices.len()
+            );
+        }
+
+        coordinates.extend(polytope.vertices.iter().flatten().map(|&x| x as i32));
+    }
+
+    Ok(coordinates)
+}
+
+const POLYTOPE_TYPE_NAMES: [&str; 3] = ["non-IP", "non-reflexive", "reflexive"];
+
+/// The data of a weight system as stored in polytope info files.
+#[derive(PartialEq, Eq)]
+struct PolytopeRecord {
+    /// 0 if not IP, 1 if not reflexive, 2 if reflexive
+    polytope_type: u8,
+    /// the vertex, facet, and point counts for IP weight systems, followed by the dual point
+    /// count and the Hodge numbers for reflexive ones
+    values: Vec<i32>,
+}
+
+impl PolytopeRecord {
+    fn write<T: BufMut>(&self, data: &mut T) {
+        data.put_u8(self.polytope_type);
+        for &x in &self.values {
+            write_varint(data, x as u32);
+        }
+    }
+
+    fn describe(&self) -> String {
+        let mut ret = POLYTOPE_TYPE_NAMES[self.polytope_type as usize].to_owned();
+        for x in &self.values {
+            ret += &format!(" {}", x);
+        }
+        ret
+    }
+}
+
+/// Determines the polytope type and data of a weight system from its polytope, and whether its
+/// Hodge numbers are included, which are only computed for five and six weights.
+fn classify_weight_system(weights: &[i32]) -> Result<(PolytopeRecord, bool)> {
+    let weights: Vec<i64> = weights.iter().map(|&w| w.into()).collect();
+
+    // the polytope is only lower-dimensional if the origin is not in its interior
+    let polytope = match Polytope::from_weights(&weights) {
+        Ok(polytope) if polytope.has_interior_origin() => polytope,
+        _ => {
+            let record = PolytopeRecord {
+                polytope_type: 0,
+                values: Vec::new(),
+            };
+            return Ok((record, true));
+        }
+    };
+
+    let mut values = vec![
+        polytope.vertices.len() as i32,
+        polytope.facets.len() as i32,
+        polytope.lattice_points().len() as i32,
+    ];
+
+    if !polytope.is_reflexive() {
+        let record = PolytopeRecord {
+            polytope_type: 1,
+            values,
+        };
+        return Ok((record, true));
+    }
+
+    values.push(polytope.dual()?.lattice_points().len() as i32);
+
+    let hodge_numbers = polytope.hodge_numbers()?;
+    if let Some(hodge_numbers) = &hodge_numbers {
+        values.extend(hodge_numbers.iter().map(|&h| h as i32));
+    }
+
+    let record = PolytopeRecord {
+        polytope_type: 2,
+        values,
+    };
+    Ok((record, hodge_numbers.is_some()))
+}
+
+/// The weight systems and their stored data.
+fn stored_records(
+    non_ip: &NonIpPolytopeInfo,
+    non_reflexive: &NonReflexivePolytopeInfo,
+    reflexive: &ReflexivePolytopeInfo,
+) -> BTreeMap<Vec<i32>, PolytopeRecord> {
+    let weights = |weight_lists: &[Vec<i32>], i: usize| -> Vec<i32> {
+        weight_lists.iter().map(|wl| wl[i]).collect()
+    };
+
+    let mut ret = BTreeMap::new();
+
+    for i in 0..non_ip.weight_lists.first().map_or(0, Vec::len) {
+        ret.insert(
+            weights(&non_ip.weight_lists, i),
+            PolytopeRecord {
+                polytope_type: 0,
+                values: Vec::new(),
+            },
+        );
+    }
+
+    for i in 0..non_reflexive.vertex_count_list.len() {
+        ret.insert(
+            weights(&non_reflexive.weight_lists, i),
+            PolytopeRecord {
+                polytope_type: 1,
+                values: vec![
+                    non_reflexive.vertex_count_list[i],
+                    non_reflexive.facet_count_list[i],
+                    non_reflexive.point_count_list[i],
+                ],
+            },
+        );
+    }
+
+    for i in 0..reflexive.vertex_count_list.len() {
+        let mut values = vec![
+            reflexive.vertex_count_list[i],
+            reflexive.facet_count_list[i],
+            reflexive.point_count_list[i],
+            reflexive.dual_point_count_list[i],
+        ];
+        values.extend(
+            reflexive.hodge_number_lists[..reflexive.ws_dimension - 3]
+                .iter()
+                .map(|h| h[i]),
+        );
+
+        ret.insert(
+            weights(&reflexive.weight_lists, i),
+            PolytopeRecord {
+                polytope_type: 2,
+                values,
+            },
+        );
+    }
+
+    ret
+}
+
+fn classify(args: IpwsClassifyArgs) -> Result<()> {
+    let limit = args.limit.unwrap_or(usize::MAX);
+
+    let mut non_ip = NonIpPolytopeInfo::default();
+    let mut non_reflexive = NonReflexivePolytopeInfo::default();
+    let mut reflexive = ReflexivePolytopeInfo::default();
+
+    let (index, weight_systems, stored) = if let Some(ws_in) = args.ws_in {
+        let (dimension, index, weights) = read_weights(ws_in, limit)?;
+        if let Some(polytope_info_in) = args.polytope_info_in {
+            (non_ip, non_reflexive, reflexive) =
+                read_polytope_info(dimension, &weights, false, polytope_info_in)?;
+        }
+
+        let weight_systems: Vec<Vec<i32>> =
+            weights.chunks(dimension).map(|ws| ws.to_vec()).collect();
+        let stored = stored_records(&non_ip, &non_reflexive, &reflexive);
+        (index, weight_systems, stored)
+    } else if !args.parquet_in.is_empty() {
+        if args.polytope_info_out.is_some() {
+            bail!("polytope info can only be written for weight system files");
+        }
+
+        let mut numerator = 0;
+        let mut denominator = 0;
+
+        for path in args.parquet_in {
+            (_, numerator, denominator) = read_parquet(
+                path,
+                &mut non_ip,
+                &mut non_reflexive,
+                &mut reflexive,
+                limit,
+                |_| true,
+            )?;
+        }
+
+        let index = format_index(numerator as u32, denominator as u32);
+
+        // the weight systems are sorted in the same way as in weight system files
+        let stored = stored_records(&non_ip, &non_reflexive, &reflexive);
+        (index, stored.keys().cloned().collect(), stored)
+    } else {
+        bail!("no input given");
+    };
+
+    if index != "1" {
+        bail!("weight systems can only be classified for index 1");
+    }
+
+    let mut pi_buf = Vec::new();
+    let mut type_counts = [0; 3];
+    let mut without_hodge_numbers = 0;
+    let mut disagreements = 0;
+
+    for weights in &weight_systems {
+        let (computed, has_hodge_numbers) = classify_weight_system(weights)?;
+        type_counts[computed.polytope_type as usize] += 1;
+
+        if !has_hodge_numbers {
+            if args.polytope_info_out.is_some() {
+                bail!("polytope info can only be written for five and six weights");
+            }
+            without_hodge_numbers += 1;
+        }
+
+        if let Some(record) = stored.get(weights) {
+            // without computed Hodge numbers only the counts before them are compared
+            let agrees = if has_hodge_numbers {
+                computed == *record
+            } else {
+                computed.polytope_type == record.polytope_type
+                    && record.values.starts_with(&computed.values)
+            };
+
+            if !agrees {
+                println!(
+                    "weight system {:?}: stored {}, computed {}",
+                    weights,
+                    record.describe(),
+                    computed.describe()
+                );
+                disagreements += 1;
+            }
+        }
+
+        computed.write(&mut pi_buf);
+    }
+
+    if let Some(polytope_info_out) = args.polytope_info_out {
+        fs::write(polytope_info_out, &pi_buf)?;
+    }
+
+    for (name, count) in POLYTOPE_TYPE_NAMES.iter().zip(type_counts) {
+        println!("{} weight system count: {}", name, count);
+    }
+    if without_hodge_numbers > 0 {
+        println!(
+            "reflexive weight systems without computed Hodge numbers: {}",
+            without_hodge_numbers
+        );
+    }
+    println!("disagreements: {}", disagreements);
+
+    if disagreements > 0 {
+        bail!("polytope info does not match");
+    }
+
+    Ok(())
+}
+
+fn gcd(a: i32, b: i32) -> i32 {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+/// Checks a single weight system, whose degree `Σ w · denominator / numerator` has to be an
+/// integer.
+fn check_weight_system(weights: &[i32], numerator: i32, denominator: i32) -> Option<&'static str> {
+    if weights.iter().any(|&w| w <= 0) {
+        return Some("weights not positive");
+    }
+
+    if weights.windows(2).any(|x| x[0] > x[1]) {
+        return Some("weights not sorted");
+    }
+
+    if weights.iter().fold(0, |g, &w| gcd(g, w)) != 1 {
+        return Some("weights not normalized");
+    }
+
+    let weight_sum: i64 = weights.iter().map(|&w| i64::from(w)).sum();
+    if weight_sum * i64::from(denominator) % i64::from(numerator) != 0 {
+        return Some("index mismatch");
+    }
+
+    None
+}
+
+/// Checks the weight systems of a shard and returns the first violation together with its row.
+fn check_weight_systems<'a>(
+    weight_systems: impl Iterator<Item = &'a [i32]>,
+    numerator: i32,
+    denominator: i32,
+) -> Option<(usize, Vec<i32>, &'static str)> {
+    let mut previous: Option<&[i32]> = None;
+
+    for (row, weights) in weight_systems.enumerate() {
+        if let Some(message) = check_weight_system(weights, numerator, denominator) {
+            return Some((row, weights.to_vec(), message));
+        }
+
+        if let Some(previous) = previous {
+            if !compare_weight_systems(previous, weights).is_lt() {
+                return Some((row, weights.to_vec(), "weight systems not sorted"));
+            }
+        }
+        previous = Some(weights);
+    }
+
+    None
+}
+
+fn check(args: IpwsCheckArgs) -> Result<()> {
+    let limit = args.limit.unwrap_or(usize::MAX);
+
+    let mut shards = Vec::new();
+
+    for path in args.ws_in {
+        let (dimension, index, weights) = read_weights(&path, limit)?;
+        let (numerator, denominator) = parse_index(&index)?;
+        shards.push((path, dimension, numerator, denominator, weights));
+    }
+
+    for path in args.parquet_in {
+        let mut non_ip = NonIpPolytopeInfo::default();
+        let mut non_reflexive = NonReflexivePolytopeInfo::default();
+        let mut reflexive = ReflexivePolytopeInfo::default();
+
+        let (dimension, numerator, denominator) = read_parquet(
+            &path,
+            &mut non_ip,
+            &mut non_reflexive,
+            &mut reflexive,
+            limit,
+            |name| name.starts_with("weight"),
+        )?;
+
+        // only one of them holds the weight systems of the file
+        let mut weights = Vec::new();
+        for weight_lists in [
+            &non_ip.weight_lists,
+            &non_reflexive.weight_lists,
+            &reflexive.weight_lists,
+        ] {
+            for i in 0..weight_lists[0].len() {
+                collect_weights_into(&mut weights, weight_lists, i);
+            }
+        }
+
+        shards.push((path, dimension, numerator, denominator, weights));
+    }
+
+    let mut failures = 0;
+
+    for (path, dimension, numerator, denominator, weights) in shards {
+        match check_weight_systems(weights.chunks(dimension), numerator, denominator) {
+            Some((row, weights, message)) => {
+                println!(
+                    "{}: row {} {:?}: {}",
+                    path.display(),
+                    row + 1,
+                    weights,
+                    message
+                );
+                failures += 1;
+            }
+            None => println!(
+                "{}: {} weight systems ok",
+                path.display(),
+                weights.len() / dimension
+            ),
+        }
+    }
+
+    if failures > 0 {
+        bail!("weight system checks failed");
+    }
+
+    Ok(())
+}
+
+/// Checks the Hodge numbers `h11, h12, h13` of a Calabi-Yau fourfold, and the stored Euler
+/// characteristic and `h22` if given.
+pub fn check_fourfold_hodge_numbers(
+    hodge_numbers: [i32; 3],
+    euler: Option<i32>,
+    h22: Option<i32>,
+) -> Vec<String> {
+    let mut ret = Vec::new();
+    let [h11, h12, h13] = hodge_numbers;
+
+    for (i, h) in hodge_numbers.iter().enumerate() {
+        if *h < 0 {
+            ret.push(format!("h1{} = {} is negative", i + 1, h));
+        }
+    }
+
+    let computed_euler =
+        euler_characteristic(&hodge_numbers.map(i64::from)).expect("three Hodge numbers") as i32;
+    if let Some(euler) = euler {
+        if euler % 6 != 0 {
+            ret.push(format!(
+                "Euler characteristic {} is not divisible by 6",
+                euler
+            ));
+        }
+        if euler != computed_euler {
+            ret.push(format!(
+                "Euler characteristic {} differs from {}",
+                euler, computed_euler
+            ));
+        }
+    }
+
+    let computed_h22 = hodge_number_h22(h11, h12, h13);
+    if computed_h22 <= 0 {
+        ret.push(format!("h22 = {} is not positive", computed_h22));
+    }
+    if let Some(h22) = h22 {
+        if h22 != computed_h22 {
+            ret.push(format!("h22 = {} differs from {}", h22, computed_h22));
+        }
+    }
+
+    ret
+}
+
+fn check_hodge(args: IpwsCheckHodgeArgs) -> Result<()> {
+    let limit = args.limit.unwrap_or(usize::MAX);
+
+    let mut violations = 0;
+    let mut count = 0;
+
+    for path in args.parquet_in {
+        let mut non_ip = NonIpPolytopeInfo::default();
+        let mut non_reflexive = NonReflexivePolytopeInfo::default();
+        let mut reflexive = ReflexivePolytopeInfo::default();
+
+        let (dimension, _, _) = read_parquet(
+            &path,
+            &mut non_ip,
+            &mut non_reflexive,
+            &mut reflexive,
+            limit,
+            |name| {
+                name.starts_with("weight")
+                    || name.starts_with('h')
+                    || name == "euler_characteristic"
+            },
+        )?;
+
+        if dimension != 6 {
+            bail!("{}: not a Calabi-Yau fourfold file", path.display());
+        }
+        if !non_ip.weight_lists[0].is_empty() || !non_reflexive.weight_lists[0].is_empty() {
+            bail!("{}: not a reflexive weight system file", path.display());
+        }
+
+        let hodge = &reflexive.hodge_number_lists;
+        let has_derived_quantities = hodge.len() == 4;
+
+        for i in 0..hodge[0].len() {
+            let (euler, h22) = if has_derived_quantities {
+                (
+                    Some(reflexive.euler_characteristic_list[i]),
+                    Some(hodge[3][i]),
+                )
+            } else {
+                (None, None)
+            };
+
+            for message in
+                check_fourfold_hodge_numbers([hodge[0][i], hodge[1][i], hodge[2][i]], euler, h22)
+            {
+                let weights: Vec<i32> = reflexive.weight_lists.iter().map(|wl| wl[i]).collect();
+                println!(
+                    "{}: row {} {:?}: {}",
+                    path.display(),
+                    i + 1,
+                    weights,
+                    message
+                );
+                violations += 1;
+            }
+
+            count += 1;
+        }
+    }
+
+    println!("weight systems checked: {}", count);
+    println!("violations: {}", violations);
+
+    if violations > 0 {
+        bail!("Hodge numbers are inconsistent");
+    }
+
+    Ok(())
+}
+
+/// Parses weight systems in the text format of PALP, with one `d w1 ... wn` line per weight
+/// system, ignoring any fields following the weights. Returns the number of weights, the index
+/// `Σ w / d` as numerator and denominator, and the weight systems.
+fn parse_palp_weights(input: &str) -> Result<(usize, i32, i32, Vec<Vec<i32>>)> {
+    let mut dimension = None;
+    let mut index = None;
+    let mut weight_systems = Vec::new();
+
+    for (i, line) in input.lines().enumerate() {
+        let mut fields = line.split_whitespace().peekable();
+
+        let Some(degree) = fields.next() else {
+            continue;
+        };
+        let degree: i64 = degree
+            .parse()
+            .with_context(|| format!("line {}: invalid degree: {}", i + 1, line))?;
+
+        let mut weights = Vec::new();
+        while let Some(w) = fields.peek().and_then(|x| x.parse::<i32>().ok()) {
+            weights.push(w);
+            fields.next();
+        }
+        weights.sort();
+
+        if degree <= 0 || weights.is_empty() || weights[0] <= 0 {
+            bail!("line {}: invalid weight system: {}", i + 1, line);
+        }
+
+        if *dimension.get_or_insert(weights.len()) != weights.len() {
+            bail!("line {}: different number of weights: {}", i + 1, line);
+        }
+
+        let weight_sum: i64 = weights.iter().map(|&w| i64::from(w)).sum();
+        let g = gcd(weight_sum.try_into()?, degree.try_into()?);
+        let ws_index = (
+            (weight_sum / i64::from(g)).try_into()?,
+            (degree / i64::from(g)).try_into()?,
+        );
+        if *index.get_or_insert(ws_index) != ws_index {
+            bail!("line {}: different index: {}", i + 1, line);
+        }
+
+        weight_systems.push(weights);
+    }
+
+    let (Some(dimension), Some((numerator, denominator))) = (dimension, index) else {
+        bail!("no weight systems found");
+    };
+
+    Ok((dimension, numerator, denominator, weight_systems))
+}
+
+fn import_palp(args: IpwsImportPalpArgs) -> Result<()> {
+    let input = fs::read_to_string(args.palp_ws_in)?;
+    let (dimension, numerator, denominator, mut weight_systems) = parse_palp_weights(&input)?;
+
+    let count = weight_systems.len();
+    weight_systems.sort_by(|a, b| compare_weight_systems(a, b));
+    weight_systems.dedup();
+
+    println!("dimension: {}", dimension);
+    println!(
+        "index: {}",
+        format_index(numerator as u32, denominator as u32)
+    );
+    println!("weight system count: {}", weight_systems.len());
+    println!("duplicates removed: {}", count - weight_systems.len());
+
+    let mut ws_buf = Vec::new();
+    write_weights_header(
+        &mut ws_buf,
+        dimension,
+        numerator,
+        denominator,
+        weight_systems.len(),
+    );
+    for &w in weight_systems.iter().flatten() {
+        write_varint(&mut ws_buf, w as u32);
+    }
+
+    fs::write(args.ws_out, &ws_buf)?;
+
+    Ok(())
+}
+
+fn export_palp(args: IpwsExportPalpArgs) -> Result<()> {
+    let limit = args.limit.unwrap_or(usize::MAX);
+
+    // the polytope data is missing for weight system files without polytope info
+    let (numerator, denominator, weight_systems) = if let Some(ws_in) = args.ws_in {
+        let (dimension, index, weights) = read_weights(ws_in, limit)?;
+        let (numerator, denominator) = parse_index(&index)?;
+
+        let weight_systems: Vec<(Vec<i32>, Option<PolytopeRecord>)> =
+            if let Some(polytope_info_in) = args.polytope_info_in {
+                let (non_ip, non_reflexive, reflexive) =
+                    read_polytope_info(dimension, &weights, false, polytope_info_in)?;
+                stored_records(&non_ip, &non_reflexive, &reflexive)
+                    .into_iter()
+                    .map(|(weights, record)| (weights, Some(record)))
+                    .collect()
+            } else {
+                weights
+                    .chunks(dimension)
+                    .map(|ws| (ws.to_vec(), None))
+                    .collect()
+            };
+
+        (numerator, denominator, weight_systems)
+    } else if !args.parquet_in.is_empty() {
+        let mut numerator = 0;
+        let mut denominator = 0;
+        let mut records = BTreeMap::new();
+
+        for path in args.parquet_in {
+            let mut non_ip = NonIpPolytopeInfo::default();
+            let mut non_reflexive = NonReflexivePolytopeInfo::default();
+            let mut reflexive = ReflexivePolytopeInfo::default();
+            (_, numerator, denominator) = read_parquet(
+                path,
+                &mut non_ip,
+                &mut non_reflexive,
+                &mut reflexive,
+                limit,
+                |_| true,
+            )?;
+            records.append(&mut stored_records(&non_ip, &non_reflexive, &reflexive));
+        }
+
+        let weight_systems = records
+            .into_iter()
+            .map(|(weights, record)| (weights, Some(record)))
+            .collect();
+
+        (numerator, denominator, weight_systems)
+    } else {
+        bail!("no input given");
+    };
+
+    let filter_h11 = args.min_h11.is_some() || args.max_h11.is_some();
+
+    let mut output = String::new();
+    let mut count = 0;
+
+    for (weights, record) in weight_systems {
+        if !args.category.is_empty() || filter_h11 {
+            let record = record.as_ref().context("filters require polytope info")?;
+
+            let category = match record.polytope_type {
+                0 => WeightSystemCategory::NonIp,
+                1 => WeightSystemCategory::NonReflexive,
+                _ => WeightSystemCategory::Reflexive,
+            };
+            if !args.category.is_empty() && !args.category.contains(&category) {
+                continue;
+            }
+
+            // h11 is only known for reflexive weight systems
+            if filter_h11 {
+                let Some(&h11) = record.values.get(4) else {
+                    continue;
+                };
+                if args.min_h11.is_some_and(|x| h11 < x) || args.max_h11.is_some_and(|x| h11 > x) {
+                    continue;
+                }
+            }
+        }
+
+        let weight_sum: i64 = weights.iter().map(|&w| i64::from(w)).sum();
+        let scaled_sum = weight_sum * i64::from(denominator);
+        if scaled_sum % i64::from(numerator) != 0 {
+            bail!("weight system {:?}: degree is not an integer", weights);
+        }
+
+        output += &(scaled_sum / i64::from(numerator)).to_string();
+        for w in &weights {
+            output += &format!(" {}", w);
+        }
+        output.push('\n');
+        count += 1;
+    }
+
+    fs::write(args.palp_ws_out, output)?;
+
+    println!("weight systems written: {}", count);
+
+    Ok(())
+}
+
+/// Writes imported records to a Parquet file of the category given by their columns.
+pub fn write_records<P: AsRef<Path>>(records: &Records, index: &str, path: P) -> Result<()> {
+    let dimension = (0..)
+        .take_while(|i| records.has_column(&format!("weight{}", i)))
+        .count();
+    if dimension < 3 {
+        bail!("weight columns missing");
+    }
+    parse_index(index)?;
+
+    let weight_names: Vec<String> = (0..dimension).map(|i| format!("weight{}", i)).collect();
+    let weight_lists = weight_names
+        .iter()
+        .map(|name| records.int_column(name))
+        .collect::<Result<Vec<_>>>()?;
+
+    let polytope_dimension = dimension - 1;
+    let mut names = weight_names.clone();
+
+    let mut non_ip = NonIpPolytopeInfo::new(dimension);
+    let mut non_reflexive = NonReflexivePolytopeInfo::new(dimension);
+    let mut reflexive = ReflexivePolytopeInfo::new(dimension, false);
+
+    if !records.has_column("vertex_count") {
+        records.check_columns(&names)?;
+        non_ip.weight_lists = weight_lists;
+
+        println!("category: non-IP");
+        return write_parquet(
+            dimension,
+            index,
+            false,
+            non_ip,
+            non_reflexive,
+            reflexive,
+            Some(ParquetOutput::parquet(fs::File::create(path)?)),
+            None,
+            None,
+            None,
+        );
+    }
+
+    let vertex_count_list = records.int_column("vertex_count")?;
+    let facet_count_list = records.int_column("facet_count")?;
+    let point_count_list = records.int_column("point_count")?;
+    names.extend(["vertex_count", "facet_count", "point_count"].map(String::from));
+
+    let coordinate_list = if records.has_column("vertices") {
+        names.push("vertices".to_owned());
+        Some(records.int_list_of_lists_column(
+            "vertices",
+            &vertex_count_list,
+            polytope_dimension,
+        )?)
+    } else {
+        None
+    };
+
+    if !records.has_column("dual_point_count") {
+        records.check_columns(&names)?;
+        non_reflexive.weight_lists = weight_lists;
+        non_reflexive.vertex_count_list = vertex_count_list;
+        non_reflexive.facet_count_list = facet_count_list;
+        non_reflexive.point_count_list = point_count_list;
+        non_reflexive.coordinate_list = coordinate_list;
+
+        println!("category: non-reflexive");
+        return write_parquet(
+            dimension,
+            index,
+            false,
+            non_ip,
+            non_reflexive,
+            reflexive,
+            None,
+            Some(ParquetOutput::parquet(fs::File::create(path)?)),
+            None,
+            None,
+        );
+    }
+
+    // the derived quantities are only written for weight systems of six weights
+    let derived_quantities = dimension == 6 && records.has_column("euler_characteristic");
+    reflexive.resize(dimension, derived_quantities);
+
+    names.push("dual_point_count".to_owned());
+    let mut hodge_number_names: Vec<String> =
+        (0..dimension - 3).map(|i| format!("h1{}", i + 1)).collect();
+    if derived_quantities {
+        hodge_number_names.push("h22".to_owned());
+        names.push("euler_characteristic".to_owned());
+    }
+    names.extend(hodge_number_names.iter().cloned());
+    records.check_columns(&names)?;
+
+    reflexive.weight_lists = weight_lists;
+    reflexive.vertex_count_list = vertex_count_list;
+    reflexive.facet_count_list = facet_count_list;
+    reflexive.point_count_list = point_count_list;
+    reflexive.dual_point_count_list = records.int_column("dual_point_count")?;
+    reflexive.hodge_number_lists = hodge_number_names
+        .iter()
+        .map(|name| records.int_column(name))
+        .collect::<Result<_>>()?;
+    if derived_quantities {
+        reflexive.euler_characteristic_list = records.int_column("euler_characteristic")?;
+    }
+    reflexive.coordinate_list = coordinate_list;
+
+    println!("category: reflexive");
+    write_parquet(
+        dimension,
+        index,
+        derived_quantities,
+        non_ip,
+        non_reflexive,
+        reflexive,
+        None,
+        None,
+        Some(ParquetOutput::parquet(fs::File::create(path)?)),
+        None,
+    )
+}
+
 fn append_metadata<W: Write + Send>(
-    writer: &mut SerializedFileWriter<W>,
+    writer: &mut OutputWriter<W>,
     ip: bool,
     reflexive: bool,
     dimension: usize,
@@ -430,16 +1293,45 @@ fn append_metadata<W: Write + Send>(
     writer.append_key_value_metadata(KeyValue::new("index".to_owned(), index.to_owned()));
 }
 
-fn write_parquet<P: AsRef<Path>>(
+/// Writes the vertices of the polytopes of a row group, whose coordinates start at
+/// `coordinate_start`, and returns where the coordinates of the next row group start.
+fn write_vertices_column<W: Write + Send>(
+    row_group_writer: &mut RowGroupWriter<W>,
+    coordinate_list: &[i32],
+    coordinate_start: usize,
+    vertex_count_list: &[i32],
+    polytope_dimension: usize,
+) -> Result<usize> {
+    let coordinate_end = coordinate_start
+        + vertex_count_list
+            .iter()
+            .map(|&count| count as usize * polytope_dimension)
+            .sum::<usize>();
+
+    write_parquet_int_list_of_lists_column(
+        row_group_writer,
+        &coordinate_list[coordinate_start..coordinate_end],
+        vertex_count_list,
+        polytope_dimension,
+    )?;
+
+    Ok(coordinate_end)
+}
+
+/// Writes the outputs that are given. With `columns`, each output only has the selected columns
+/// that it has, in its usual order.
+#[allow(clippy::too_many_arguments)]
+fn write_parquet<W: Write + Send>(
     dimension: usize,
     index: &str,
     write_derived_quantities: bool,
     non_ip: NonIpPolytopeInfo,
     non_reflexive: NonReflexivePolytopeInfo,
     reflexive: ReflexivePolytopeInfo,
-    non_ip_path: Option<P>,
-    non_reflexive_path: Option<P>,
-    reflexive_path: Option<P>,
+    non_ip_out: Option<ParquetOutput<W>>,
+    non_reflexive_out: Option<ParquetOutput<W>>,
+    reflexive_out: Option<ParquetOutput<W>>,
+    columns: Option<&[String]>,
 ) -> Result<()> {
     use parquet::basic::{Compression, ZstdLevel};
     use parquet::file::properties::{WriterProperties, WriterVersion};
@@ -473,19 +1365,69 @@ fn write_parquet<P: AsRef<Path>>(
     let point_count_field = build_parquet_int_field("point_count")?;
     let dual_point_count_field = build_parquet_int_field("dual_point_count")?;
     let euler_characteristic_field = build_parquet_int_field("euler_characteristic")?;
+    let vertices_field = build_parquet_int_list_of_lists_field("vertices")?;
+
+    let non_ip_fields = weight_fields.clone();
+
+    let mut non_reflexive_fields = weight_fields.clone();
+    non_reflexive_fields.push(vertex_count_field.clone());
+    non_reflexive_fields.push(facet_count_field.clone());
+    non_reflexive_fields.push(point_count_field.clone());
+    if non_reflexive.coordinate_list.is_some() {
+        non_reflexive_fields.push(vertices_field.clone());
+    }
+
+    let mut reflexive_fields = weight_fields.clone();
+    reflexive_fields.push(vertex_count_field.clone());
+    reflexive_fields.push(facet_count_field.clone());
+    reflexive_fields.push(point_count_field.clone());
+    reflexive_fields.push(dual_point_count_field.clone());
+    reflexive_fields.append(&mut hodge_number_fields.clone());
+    if write_derived_quantities && dimension == 6 {
+        reflexive_fields.push(euler_characteristic_field.clone());
+    }
+    if reflexive.coordinate_list.is_some() {
+        reflexive_fields.push(vertices_field.clone());
+    }
+
+    let selected = |name: &str| columns.is_none_or(|columns| columns.iter().any(|x| x == name));
+
+    // each selected column has to be in one of the outputs, and each output has to keep a column
+    let outputs = [
+        ("non-IP", non_ip_out.is_some(), &non_ip_fields),
+        (
+            "non-reflexive",
+            non_reflexive_out.is_some(),
+            &non_reflexive_fields,
+        ),
+        ("reflexive", reflexive_out.is_some(), &reflexive_fields),
+    ];
+    for name in columns.unwrap_or_default() {
+        let known = outputs
+            .iter()
+            .any(|(_, written, fields)| *written && fields.iter().any(|f| f.name() == name));
+        if !known {
+            bail!("unknown column {}", name);
+        }
+    }
+    for (category, written, fields) in outputs {
+        if written && !fields.iter().any(|f| selected(f.name())) {
+            bail!("the {} output has none of the selected columns", category);
+        }
+    }
+
+    if let Some(non_ip_out) = non_ip_out {
+        let mut non_ip_fields = non_ip_fields;
+        non_ip_fields.retain(|f| selected(f.name()));
 
-    if let Some(non_ip_path) = non_ip_path {
         let non_ip_schema = Type::group_type_builder("schema")
-            .with_fields(weight_fields.clone())
+            .with_fields(non_ip_fields)
             .build()?;
 
-        let file = fs::File::create(non_ip_path)?;
-
         let row_count = non_ip.weight_lists[0].len();
-        let row_group_count = (row_count + ROW_GROUP_SIZE - 1) / ROW_GROUP_SIZE;
+        let row_group_count = row_count.div_ceil(ROW_GROUP_SIZE);
 
-        let mut writer =
-            SerializedFileWriter::new(file, Arc::new(non_ip_schema), writer_props.clone())?;
+        let mut writer = non_ip_out.writer(Arc::new(non_ip_schema), writer_props.clone())?;
 
         append_metadata(&mut writer, false, false, dimension, index);
 
@@ -496,8 +1438,10 @@ fn write_parquet<P: AsRef<Path>>(
 
             let mut row_group_writer = writer.next_row_group()?;
 
-            for weights in &non_ip.weight_lists {
-                write_parquet_int_column(&mut row_group_writer, &weights[start..end])?;
+            for (field, weights) in weight_fields.iter().zip(&non_ip.weight_lists) {
+                if selected(field.name()) {
+                    write_parquet_int_column(&mut row_group_writer, &weights[start..end])?;
+                }
             }
 
             row_group_writer.close()?;
@@ -506,26 +1450,24 @@ fn write_parquet<P: AsRef<Path>>(
         writer.close()?;
     }
 
-    if let Some(non_reflexive_path) = non_reflexive_path {
-        let mut non_reflexive_fields = weight_fields.clone();
-        non_reflexive_fields.push(vertex_count_field.clone());
-        non_reflexive_fields.push(facet_count_field.clone());
-        non_reflexive_fields.push(point_count_field.clone());
+    if let Some(non_reflexive_out) = non_reflexive_out {
+        let mut non_reflexive_fields = non_reflexive_fields;
+        non_reflexive_fields.retain(|f| selected(f.name()));
 
         let non_reflexive_schema = Type::group_type_builder("schema")
             .with_fields(non_reflexive_fields)
             .build()?;
 
-        let file = fs::File::create(non_reflexive_path)?;
-
         let row_count = non_reflexive.weight_lists[0].len();
-        let row_group_count = (row_count + ROW_GROUP_SIZE - 1) / ROW_GROUP_SIZE;
+        let row_group_count = row_count.div_ceil(ROW_GROUP_SIZE);
 
         let mut writer =
-            SerializedFileWriter::new(file, Arc::new(non_reflexive_schema), writer_props.clone())?;
+            non_reflexive_out.writer(Arc::new(non_reflexive_schema), writer_props.clone())?;
 
         append_metadata(&mut writer, true, false, dimension, index);
 
+        let mut coordinate_end = 0;
+
         for g in 0..row_group_count {
             let start = g * ROW_GROUP_SIZE;
             let end = min(start + ROW_GROUP_SIZE, row_count);
@@ -533,22 +1475,33 @@ fn write_parquet<P: AsRef<Path>>(
 
             let mut row_group_writer = writer.next_row_group()?;
 
-            for weights in &non_reflexive.weight_lists {
-                write_parquet_int_column(&mut row_group_writer, &weights[start..end])?;
+            for (field, weights) in weight_fields.iter().zip(&non_reflexive.weight_lists) {
+                if selected(field.name()) {
+                    write_parquet_int_column(&mut row_group_writer, &weights[start..end])?;
+                }
+            }
+
+            for (field, list) in [
+                (&vertex_count_field, &non_reflexive.vertex_count_list),
+                (&facet_count_field, &non_reflexive.facet_count_list),
+                (&point_count_field, &non_reflexive.point_count_list),
+            ] {
+                if selected(field.name()) {
+                    write_parquet_int_column(&mut row_group_writer, &list[start..end])?;
+                }
             }
 
-            write_parquet_int_column(
-                &mut row_group_writer,
-                &non_reflexive.vertex_count_list[start..end],
-            )?;
-            write_parquet_int_column(
-                &mut row_group_writer,
-                &non_reflexive.facet_count_list[start..end],
-            )?;
-            write_parquet_int_column(
-                &mut row_group_writer,
-                &non_reflexive.point_count_list[start..end],
-            )?;
+            if let Some(coordinate_list) = &non_reflexive.coordinate_list {
+                if selected(vertices_field.name()) {
+                    coordinate_end = write_vertices_column(
+                        &mut row_group_writer,
+                        coordinate_list,
+                        coordinate_end,
+                        &non_reflexive.vertex_count_list[start..end],
+                        dimension - 1,
+                    )?;
+                }
+            }
 
             row_group_writer.close()?;
         }
@@ -556,31 +1509,23 @@ fn write_parquet<P: AsRef<Path>>(
         writer.close()?;
     }
 
-    if let Some(reflexive_path) = reflexive_path {
-        let mut reflexive_fields = weight_fields.clone();
-        reflexive_fields.push(vertex_count_field.clone());
-        reflexive_fields.push(facet_count_field.clone());
-        reflexive_fields.push(point_count_field.clone());
-        reflexive_fields.push(dual_point_count_field.clone());
-        reflexive_fields.append(&mut hodge_number_fields.clone());
-        if write_derived_quantities && dimension == 6 {
-            reflexive_fields.push(euler_characteristic_field.clone());
-        }
+    if let Some(reflexive_out) = reflexive_out {
+        let mut reflexive_fields = reflexive_fields;
+        reflexive_fields.retain(|f| selected(f.name()));
 
         let reflexive_schema = Type::group_type_builder("schema")
             .with_fields(reflexive_fields)
             .build()?;
 
-        let file = fs::File::create(reflexive_path)?;
-
         let row_count = reflexive.weight_lists[0].len();
-        let row_group_count = (row_count + ROW_GROUP_SIZE - 1) / ROW_GROUP_SIZE;
+        let row_group_count = row_count.div_ceil(ROW_GROUP_SIZE);
 
-        let mut writer =
-            SerializedFileWriter::new(file, Arc::new(reflexive_schema), writer_props.clone())?;
+        let mut writer = reflexive_out.writer(Arc::new(reflexive_schema), writer_props.clone())?;
 
         append_metadata(&mut writer, true, true, dimension, index);
 
+        let mut coordinate_end = 0;
+
         for g in 0..row_group_count {
             let start = g * ROW_GROUP_SIZE;
             let end = min(start + ROW_GROUP_SIZE, row_count);
@@ -588,38 +1533,54 @@ fn write_parquet<P: AsRef<Path>>(
 
             let mut row_group_writer = writer.next_row_group()?;
 
-            for weights in &reflexive.weight_lists {
-                write_parquet_int_column(&mut row_group_writer, &weights[start..end])?;
+            for (field, weights) in weight_fields.iter().zip(&reflexive.weight_lists) {
+                if selected(field.name()) {
+                    write_parquet_int_column(&mut row_group_writer, &weights[start..end])?;
+                }
             }
 
-            write_parquet_int_column(
-                &mut row_group_writer,
-                &reflexive.vertex_count_list[start..end],
-            )?;
-            write_parquet_int_column(
-                &mut row_group_writer,
-                &reflexive.facet_count_list[start..end],
-            )?;
-            write_parquet_int_column(
-                &mut row_group_writer,
-                &reflexive.point_count_list[start..end],
-            )?;
-            write_parquet_int_column(
-                &mut row_group_writer,
-                &reflexive.dual_point_count_list[start..end],
-            )?;
+            for (field, list) in [
+                (&vertex_count_field, &reflexive.vertex_count_list),
+                (&facet_count_field, &reflexive.facet_count_list),
+                (&point_count_field, &reflexive.point_count_list),
+                (&dual_point_count_field, &reflexive.dual_point_count_list),
+            ] {
+                if selected(field.name()) {
+                    write_parquet_int_column(&mut row_group_writer, &list[start..end])?;
+                }
+            }
 
-            for h in &reflexive.hodge_number_lists {
-                write_parquet_int_column(&mut row_group_writer, &h[start..end])?;
+            for (field, h) in hodge_number_fields
+                .iter()
+                .zip(&reflexive.hodge_number_lists)
+            {
+                if selected(field.name()) {
+                    write_parquet_int_column(&mut row_group_writer, &h[start..end])?;
+                }
             }
 
-            if write_derived_quantities && dimension == 6 {
+            if write_derived_quantities
+                && dimension == 6
+                && selected(euler_characteristic_field.name())
+            {
                 write_parquet_int_column(
                     &mut row_group_writer,
                     &reflexive.euler_characteristic_list[start..end],
                 )?;
             }
 
+            if let Some(coordinate_list) = &reflexive.coordinate_list {
+                if selected(vertices_field.name()) {
+                    coordinate_end = write_vertices_column(
+                        &mut row_group_writer,
+                        coordinate_list,
+                        coordinate_end,
+                        &reflexive.vertex_count_list[start..end],
+                        dimension - 1,
+                    )?;
+                }
+            }
+
             row_group_writer.close()?;
         }
 
@@ -653,26 +1614,33 @@ fn parse_parquet_metadata(metadata: &[KeyValue]) -> Result<(bool, bool, usize, i
         bail!("missing Parquet file metadata");
     };
 
+    let (numerator, denominator) = parse_index(&index)?;
+
+    Ok((ip, reflexive, dimension, numerator, denominator))
+}
+
+fn parse_index(index: &str) -> Result<(i32, i32)> {
     let re = Regex::new(r"^([0-9]+)/([0-9]+)$").unwrap();
 
-    let (numerator, denominator): (i32, i32) = if let Some(c) = re.captures(&index) {
-        (
+    if let Some(c) = re.captures(index) {
+        Ok((
             FromStr::from_str(c.get(1).expect("capture").as_str()).context("parse index")?,
             FromStr::from_str(c.get(2).expect("capture").as_str()).context("parse index")?,
-        )
+        ))
     } else {
-        (FromStr::from_str(&index)?, 1)
-    };
-
-    Ok((ip, reflexive, dimension, numerator, denominator))
+        Ok((FromStr::from_str(index)?, 1))
+    }
 }
 
+/// Reads a Parquet file of one category. Only the columns accepted by `is_selected` are decoded,
+/// the lists of the others are left empty.
 fn read_parquet<P: AsRef<Path>>(
     path: P,
     non_ip: &mut NonIpPolytopeInfo,
     non_reflexive: &mut NonReflexivePolytopeInfo,
     reflexive: &mut ReflexivePolytopeInfo,
     limit: usize,
+    is_selected: impl Fn(&str) -> bool,
 ) -> Result<(usize, i32, i32)> {
     use parquet::column::reader::ColumnReader;
     use parquet::file::reader::FileReader as _;
@@ -688,41 +1656,80 @@ fn read_parquet<P: AsRef<Path>>(
         .context("no Parquet file metadata")?;
 
     let (is_ip, is_reflexive, dimension, numerator, denominator) =
-        parse_parquet_metadata(&kv_metadata)?;
+        parse_parquet_metadata(kv_metadata)?;
 
-    let num_columns = match (is_ip, is_reflexive) {
-        (false, false) => dimension,
-        (true, false) => dimension + 3,
-        (true, true) => 2 * dimension + 1,
-        _ => bail!("invalid metadata"),
-    };
+    if !is_ip && is_reflexive {
+        bail!("invalid metadata");
+    }
 
     non_ip.resize(dimension);
     non_reflexive.resize(dimension);
     reflexive.resize(dimension, false);
 
+    // the columns are found by name, since files written with a column selection might lack some
+    let schema = metadata.file_metadata().schema_descr();
+    let position =
+        |name: &str| (0..schema.num_columns()).find(|&c| schema.column(c).name() == name);
+
+    let mut names: Vec<String> = (0..dimension).map(|i| format!("weight{}", i)).collect();
+    if is_ip {
+        names.extend(["vertex_count", "facet_count", "point_count"].map(String::from));
+    }
+    if is_reflexive {
+        names.push("dual_point_count".to_owned());
+        names.extend((0..dimension - 3).map(|i| format!("h1{}", i + 1)));
+    }
+
+    // the derived quantities h22 and the Euler characteristic are read if present
+    let has_derived_quantities =
+        is_reflexive && position("h22").is_some() && position("euler_characteristic").is_some();
+    if has_derived_quantities {
+        names.extend(["h22", "euler_characteristic"].map(String::from));
+    }
+
+    // columns that are not selected are left empty and need not be present
+    let columns: Vec<Option<usize>> = names
+        .iter()
+        .map(|name| match is_selected(name) {
+            true => position(name)
+                .map(Some)
+                .with_context(|| format!("column {} missing", name)),
+            false => Ok(None),
+        })
+        .collect::<Result<_>>()?;
+
     let row_count = min(metadata.file_metadata().num_rows() as usize, limit);
 
-    let mut values = vec![vec![0; row_count]; num_columns];
+    let mut values: Vec<Vec<i32>> = columns
+        .iter()
+        .map(|c| match c {
+            Some(_) => vec![0; row_count],
+            None => Vec::new(),
+        })
+        .collect();
     let mut pos = 0;
 
     for g in 0..metadata.num_row_groups() {
         let row_group_reader = reader.get_row_group(g)?;
         let row_group_metadata = metadata.row_group(g);
 
-        if num_columns > row_group_metadata.num_columns() {
-            bail!("columns missing");
-        }
-
         let to_read = min(row_group_metadata.num_rows() as usize, row_count - pos);
 
-        for c in 0..num_columns {
+        for (&c, column_values) in columns.iter().zip(values.iter_mut()) {
+            let Some(c) = c else {
+                continue;
+            };
+
             let mut column_reader = row_group_reader.get_column_reader(c)?;
 
             match column_reader {
                 ColumnReader::Int32ColumnReader(ref mut typed_reader) => {
-                    let (count, _, _) =
-                        typed_reader.read_records(to_read, None, None, &mut values[c][pos..])?;
+                    let (count, _, _) = typed_reader.read_records(
+                        to_read,
+                        None,
+                        None,
+                        &mut column_values[pos..],
+                    )?;
 
                     assert_eq!(count, to_read);
                 }
@@ -753,12 +1760,26 @@ fn read_parquet<P: AsRef<Path>>(
         reflexive.dual_point_count_list = values.remove(0);
         reflexive.hodge_number_lists = values.drain(0..dimension - 3).collect();
         reflexive.euler_characteristic_list = Vec::new();
+        if has_derived_quantities {
+            reflexive.hodge_number_lists.push(values.remove(0));
+            reflexive.euler_characteristic_list = values.remove(0);
+        }
     }
 
     Ok((dimension, numerator, denominator))
 }
 
 pub fn run(args: IpwsArgs) -> Result<()> {
+    if let Some(command) = args.command {
+        return match command {
+            IpwsCommands::Classify(args) => classify(args),
+            IpwsCommands::Check(args) => check(args),
+            IpwsCommands::CheckHodge(args) => check_hodge(args),
+            IpwsCommands::ImportPalp(args) => import_palp(args),
+            IpwsCommands::ExportPalp(args) => export_palp(args),
+        };
+    }
+
     let limit = args.limit.unwrap_or(usize::MAX);
 
     if let (Some(ws_in), Some(polytope_info_in)) = (args.ws_in, args.polytope_info_in) {
@@ -766,13 +1787,32 @@ pub fn run(args: IpwsArgs) -> Result<()> {
         let (dimension, index, weights) = read_weights(ws_in, limit)?;
 
         println!("Reading polytope info...");
-        let (non_ip, non_reflexive, reflexive) = read_polytope_info(
+        let (non_ip, mut non_reflexive, mut reflexive) = read_polytope_info(
             dimension,
             &weights,
             args.include_derived_quantities,
             polytope_info_in,
         )?;
 
+        if args.with_vertices {
+            println!("Computing vertices...");
+            non_reflexive.coordinate_list = Some(compute_vertices(
+                &non_reflexive.weight_lists,
+                &non_reflexive.vertex_count_list,
+                &index,
+            )?);
+            reflexive.coordinate_list = Some(compute_vertices(
+                &reflexive.weight_lists,
+                &reflexive.vertex_count_list,
+                &index,
+            )?);
+        }
+
+        let non_ip_out = ParquetOutput::new(args.parquet_non_ip_out, args.ipc_non_ip_out)?;
+        let non_reflexive_out =
+            ParquetOutput::new(args.parquet_non_reflexive_out, args.ipc_non_reflexive_out)?;
+        let reflexive_out = ParquetOutput::new(args.parquet_reflexive_out, args.ipc_reflexive_out)?;
+
         println!("Writing Parquet...");
         write_parquet(
             dimension,
@@ -781,9 +1821,10 @@ pub fn run(args: IpwsArgs) -> Result<()> {
             non_ip,
             non_reflexive,
             reflexive,
-            args.parquet_non_ip_out,
-            args.parquet_non_reflexive_out,
-            args.parquet_reflexive_out,
+            non_ip_out,
+            non_reflexive_out,
+            reflexive_out,
+            args.columns.as_deref(),
         )?;
     } else if !args.parquet_in.is_empty() {
         println!("Reading Parquet...");
@@ -797,8 +1838,14 @@ pub fn run(args: IpwsArgs) -> Result<()> {
         let mut denominator = 0;
 
         for path in args.parquet_in {
-            (dimension, numerator, denominator) =
-                read_parquet(path, &mut non_ip, &mut non_reflexive, &mut reflexive, limit)?;
+            (dimension, numerator, denominator) = read_parquet(
+                path,
+                &mut non_ip,
+                &mut non_reflexive,
+                &mut reflexive,
+                limit,
+                |_| true,
+            )?;
         }
 
         println!("Writing weights and polytope info...");
@@ -818,3 +1865,190 @@ pub fn run(args: IpwsArgs) -> Result<()> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    #[test]
+    fn classified_weight_systems() {
+        let (record, has_hodge_numbers) = classify_weight_system(&[1, 1, 1, 1, 1]).unwrap();
+        assert_eq!(record.describe(), "reflexive 5 5 126 6 1 101");
+        assert!(has_hodge_numbers);
+
+        let (record, _) = classify_weight_system(&[1, 1, 1, 1, 4]).unwrap();
+        assert_eq!(record.describe(), "reflexive 5 5 201 7 1 149");
+
+        // the Hodge numbers of K3 surfaces are not computed
+        let (record, has_hodge_numbers) = classify_weight_system(&[1, 1, 1, 1]).unwrap();
+        assert_eq!(record.describe(), "reflexive 4 4 35 5");
+        assert!(!has_hodge_numbers);
+
+        let (record, _) = classify_weight_system(&[1, 1, 1, 1, 5]).unwrap();
+        assert_eq!(record.describe(), "non-IP");
+    }
+
+    #[test]
+    fn checked_weight_systems() {
+        assert_eq!(check_weight_system(&[1, 1, 1, 1, 1], 1, 1), None);
+        assert_eq!(check_weight_system(&[1, 1, 1, 1, 2], 1, 1), None);
+        assert_eq!(
+            check_weight_system(&[0, 1, 1, 1, 1], 1, 1),
+            Some("weights not positive")
+        );
+        assert_eq!(
+            check_weight_system(&[1, 1, 1, 2, 1], 1, 1),
+            Some("weights not sorted")
+        );
+        assert_eq!(
+            check_weight_system(&[2, 2, 2, 2, 2], 1, 1),
+            Some("weights not normalized")
+        );
+        // the degree 6/4 of the weights is not an integer
+        assert_eq!(
+            check_weight_system(&[1, 1, 1, 1, 2], 4, 1),
+            Some("index mismatch")
+        );
+        assert_eq!(check_weight_system(&[1, 1, 1, 1, 2], 3, 2), None);
+
+        let weights = [1, 1, 1, 1, 1, 1, 1, 1, 1, 2, 1, 1, 1, 2, 2];
+        assert_eq!(check_weight_systems(weights.chunks(5), 1, 1), None);
+        assert_eq!(
+            check_weight_systems(weights.chunks(5).rev(), 1, 1),
+            Some((1, vec![1, 1, 1, 1, 2], "weight systems not sorted"))
+        );
+    }
+
+    fn temporary_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("{}-{}", name, std::process::id()))
+    }
+
+    #[test]
+    fn palp_weights() {
+        let input = "\
+5 1 1 1 1 1
+6 2 1 1 1 1 M:130 5 N:6 5 H:1,103 [-204]
+
+6 1 1 1 1 2
+";
+        let (dimension, numerator, denominator, weight_systems) =
+            parse_palp_weights(input).unwrap();
+        assert_eq!((dimension, numerator, denominator), (5, 1, 1));
+        assert_eq!(
+            weight_systems,
+            [[1, 1, 1, 1, 1], [1, 1, 1, 1, 2], [1, 1, 1, 1, 2]]
+        );
+
+        let (_, numerator, denominator, _) = parse_palp_weights("10 1 1 1 1 1\n").unwrap();
+        assert_eq!((numerator, denominator), (1, 2));
+
+        for (input, message) in [
+            (
+                "5 1 1 1 1 1\n4 1 1 1 1\n",
+                "line 2: different number of weights: 4 1 1 1 1",
+            ),
+            (
+                "5 1 1 1 1 1\n10 1 1 1 1 1\n",
+                "line 2: different index: 10 1 1 1 1 1",
+            ),
+            ("x 1 1 1 1 1\n", "line 1: invalid degree: x 1 1 1 1 1"),
+            (
+                "5 0 1 1 1 1\n",
+                "line 1: invalid weight system: 5 0 1 1 1 1",
+            ),
+            ("\n", "no weight systems found"),
+        ] {
+            let error = parse_palp_weights(input).unwrap_err();
+            assert_eq!(error.to_string(), message);
+        }
+
+        let palp_ws_in = temporary_path("import.txt");
+        let ws_out = temporary_path("import.ws");
+        fs::write(&palp_ws_in, input).unwrap();
+        import_palp(IpwsImportPalpArgs {
+            palp_ws_in: palp_ws_in.clone(),
+            ws_out: ws_out.clone(),
+        })
+        .unwrap();
+
+        // the weight systems are sorted and duplicates are removed
+        let (dimension, index, weights) = read_weights(&ws_out, usize::MAX).unwrap();
+        assert_eq!((dimension, index.as_str()), (5, "1"));
+        assert_eq!(weights, [1, 1, 1, 1, 1, 1, 1, 1, 1, 2]);
+
+        fs::remove_file(palp_ws_in).unwrap();
+        fs::remove_file(ws_out).unwrap();
+    }
+
+    #[test]
+    fn exported_palp_weights() {
+        let weight_systems = [
+            [1, 1, 1, 1, 1],
+            [1, 1, 1, 1, 4],
+            [1, 1, 1, 1, 5],
+            [1, 1, 1, 2, 2],
+        ];
+
+        let mut ws_buf = Vec::new();
+        let mut pi_buf = Vec::new();
+        write_weights_header(&mut ws_buf, 5, 1, 1, weight_systems.len());
+        for weights in &weight_systems {
+            for &w in weights {
+                write_varint(&mut ws_buf, w as u32);
+            }
+            classify_weight_system(weights)
+                .unwrap()
+                .0
+                .write(&mut pi_buf);
+        }
+
+        let ws_in = temporary_path("export.ws");
+        let polytope_info_in = temporary_path("export.info");
+        let palp_ws_out = temporary_path("export.txt");
+        fs::write(&ws_in, ws_buf).unwrap();
+        fs::write(&polytope_info_in, pi_buf).unwrap();
+
+        let export = |polytope_info_in: Option<&PathBuf>,
+                      category: Vec<WeightSystemCategory>,
+                      min_h11: Option<i32>| {
+            export_palp(IpwsExportPalpArgs {
+                ws_in: Some(ws_in.clone()),
+                polytope_info_in: polytope_info_in.cloned(),
+                parquet_in: Vec::new(),
+                palp_ws_out: palp_ws_out.clone(),
+                category,
+                min_h11,
+                max_h11: None,
+                limit: None,
+            })
+            .map(|()| fs::read_to_string(&palp_ws_out).unwrap())
+        };
+
+        assert_eq!(
+            export(None, Vec::new(), None).unwrap(),
+            "5 1 1 1 1 1\n8 1 1 1 1 4\n9 1 1 1 1 5\n7 1 1 1 2 2\n"
+        );
+        assert_eq!(
+            export(
+                Some(&polytope_info_in),
+                vec![WeightSystemCategory::NonIp],
+                None
+            )
+            .unwrap(),
+            "9 1 1 1 1 5\n"
+        );
+        assert_eq!(
+            export(Some(&polytope_info_in), Vec::new(), Some(2)).unwrap(),
+            "7 1 1 1 2 2\n"
+        );
+
+        let error = export(None, Vec::new(), Some(2)).unwrap_err();
+        assert_eq!(error.to_string(), "filters require polytope info");
+
+        for path in [ws_in, polytope_info_in, palp_ws_out] {
+            fs::remove_file(path).unwrap();
+        }
+    }
+}
@@ -1,11 +1,17 @@
 use std::path::PathBuf;
 
 use anyhow::Result;
-use clap::{Args, Parser, Subcommand};
+use clap::{Args, Parser, Subcommand, ValueEnum};
 
+mod export;
+mod filter;
+mod import;
+mod ipc_utils;
 mod ipws;
+mod npz;
 mod palp;
 mod parquet_utils;
+mod polytope;
 
 #[derive(Parser)]
 #[command(version)]
@@ -16,27 +22,132 @@ struct Cli {
 
 #[derive(Subcommand)]
 enum Commands {
-    Ipws(IpwsArgs),
-    Palp(PalpArgs),
+    Ipws(Box<IpwsArgs>),
+    Palp(Box<PalpArgs>),
+    /// Write a Parquet file of this program in another format
+    Export(ExportArgs),
+    /// Convert records in a text format to a Parquet file of this program
+    Import(ImportArgs),
+    /// Write the rows of a Parquet file of this program that satisfy a condition
+    Filter(FilterArgs),
 }
 
 #[derive(Args)]
 struct PalpArgs {
+    #[command(subcommand)]
+    command: Option<PalpCommands>,
+
     #[arg(long, value_name = "FILE")]
     palp_in: Option<PathBuf>,
 
     #[arg(long, value_name = "FILE")]
     palp_out: Option<PathBuf>,
 
+    #[arg(long, value_enum)]
+    palp_layout: Option<PalpMatrixLayout>,
+
     #[arg(long, value_name = "FILE")]
     parquet_in: Option<PathBuf>,
 
     #[arg(long, value_name = "FILE")]
     parquet_out: Option<PathBuf>,
+
+    #[arg(long, value_name = "FILE")]
+    ipc_out: Option<PathBuf>,
+
+    #[arg(long, value_delimiter = ',')]
+    columns: Option<Vec<String>>,
+
+    #[arg(long, value_name = "DIR")]
+    polymake_out: Option<PathBuf>,
+
+    #[arg(long, value_name = "FILE")]
+    sage_out: Option<PathBuf>,
+
+    #[arg(long)]
+    limit: Option<usize>,
+
+    #[arg(long)]
+    preserve_layout: bool,
+
+    #[arg(long)]
+    fill_header: bool,
+
+    #[arg(long)]
+    with_dual: bool,
+
+    #[arg(long)]
+    with_normal_form: bool,
+
+    #[arg(long)]
+    with_normal_form_hash: bool,
+
+    #[arg(long, requires = "reject_out")]
+    skip_invalid: bool,
+
+    #[arg(long, value_name = "FILE", requires = "skip_invalid")]
+    reject_out: Option<PathBuf>,
+}
+
+#[derive(Subcommand)]
+enum PalpCommands {
+    /// Recompute the M: and N: lattice point counts from the vertices
+    VerifyCounts(PalpVerifyArgs),
+    /// Check the Hodge numbers and Euler characteristics for consistency
+    CheckHodge(PalpCheckHodgeArgs),
+    /// Recompute the Hodge numbers and the Euler characteristic of 4d and 5d reflexive polytopes
+    VerifyHodge(PalpVerifyArgs),
+    /// Report polytopes with the same normal form
+    Dedupe(PalpDedupeArgs),
+}
+
+#[derive(Args)]
+struct PalpVerifyArgs {
+    #[arg(long, value_name = "FILE")]
+    palp_in: Option<PathBuf>,
+
+    #[arg(long, value_name = "FILE")]
+    parquet_in: Option<PathBuf>,
+
+    #[arg(long)]
+    limit: Option<usize>,
+}
+
+#[derive(Args)]
+struct PalpCheckHodgeArgs {
+    #[arg(long, value_name = "FILE")]
+    palp_in: Option<PathBuf>,
+
+    #[arg(long, value_name = "FILE")]
+    parquet_in: Option<PathBuf>,
+
+    #[arg(long)]
+    limit: Option<usize>,
+}
+
+#[derive(Args)]
+struct PalpDedupeArgs {
+    #[arg(long, value_name = "FILE")]
+    palp_in: Vec<PathBuf>,
+
+    #[arg(long, value_name = "FILE")]
+    parquet_in: Vec<PathBuf>,
+}
+
+/// Orientation of the vertex matrices in PALP text
+#[derive(Clone, Copy, ValueEnum)]
+enum PalpMatrixLayout {
+    /// one vertex per column (`dim × nverts`)
+    Columns,
+    /// one vertex per row (`nverts × dim`)
+    Rows,
 }
 
 #[derive(Args)]
 struct IpwsArgs {
+    #[command(subcommand)]
+    command: Option<IpwsCommands>,
+
     #[arg(long, value_name = "FILE")]
     ws_in: Option<PathBuf>,
 
@@ -61,18 +172,210 @@ struct IpwsArgs {
     #[arg(long, value_name = "FILE")]
     parquet_reflexive_out: Option<PathBuf>,
 
+    #[arg(long, value_name = "FILE")]
+    ipc_non_ip_out: Option<PathBuf>,
+
+    #[arg(long, value_name = "FILE")]
+    ipc_non_reflexive_out: Option<PathBuf>,
+
+    #[arg(long, value_name = "FILE")]
+    ipc_reflexive_out: Option<PathBuf>,
+
+    #[arg(long, value_delimiter = ',')]
+    columns: Option<Vec<String>>,
+
     #[arg(short, long)]
     include_derived_quantities: bool,
 
+    #[arg(long)]
+    with_vertices: bool,
+
     #[arg(long)]
     limit: Option<usize>,
 }
 
+#[derive(Subcommand)]
+enum IpwsCommands {
+    /// Recompute the polytope types and data from the weights
+    Classify(IpwsClassifyArgs),
+    /// Check the index, normalization, and order of weight systems
+    Check(IpwsCheckArgs),
+    /// Check the Hodge numbers of reflexive weight systems for consistency
+    CheckHodge(IpwsCheckHodgeArgs),
+    /// Convert weight systems in the text format of PALP to a weight system file
+    ImportPalp(IpwsImportPalpArgs),
+    /// Write weight systems in the text format of PALP
+    ExportPalp(IpwsExportPalpArgs),
+}
+
+#[derive(Args)]
+struct IpwsClassifyArgs {
+    #[arg(long, value_name = "FILE")]
+    ws_in: Option<PathBuf>,
+
+    #[arg(long, value_name = "FILE")]
+    polytope_info_in: Option<PathBuf>,
+
+    #[arg(long, value_name = "FILE")]
+    parquet_in: Vec<PathBuf>,
+
+    #[arg(long, value_name = "FILE")]
+    polytope_info_out: Option<PathBuf>,
+
+    #[arg(long)]
+    limit: Option<usize>,
+}
+
+#[derive(Args)]
+struct IpwsCheckArgs {
+    #[arg(long, value_name = "FILE")]
+    ws_in: Vec<PathBuf>,
+
+    #[arg(long, value_name = "FILE")]
+    parquet_in: Vec<PathBuf>,
+
+    #[arg(long)]
+    limit: Option<usize>,
+}
+
+#[derive(Args)]
+struct IpwsCheckHodgeArgs {
+    #[arg(long, value_name = "FILE")]
+    parquet_in: Vec<PathBuf>,
+
+    #[arg(long)]
+    limit: Option<usize>,
+}
+
+#[derive(Args)]
+struct IpwsImportPalpArgs {
+    #[arg(long, value_name = "FILE")]
+    palp_ws_in: PathBuf,
+
+    #[arg(long, value_name = "FILE")]
+    ws_out: PathBuf,
+}
+
+#[derive(Args)]
+struct IpwsExportPalpArgs {
+    #[arg(long, value_name = "FILE")]
+    ws_in: Option<PathBuf>,
+
+    #[arg(long, value_name = "FILE")]
+    polytope_info_in: Option<PathBuf>,
+
+    #[arg(long, value_name = "FILE")]
+    parquet_in: Vec<PathBuf>,
+
+    #[arg(long, value_name = "FILE")]
+    palp_ws_out: PathBuf,
+
+    #[arg(long, value_enum)]
+    category: Vec<WeightSystemCategory>,
+
+    #[arg(long)]
+    min_h11: Option<i32>,
+
+    #[arg(long)]
+    max_h11: Option<i32>,
+
+    #[arg(long)]
+    limit: Option<usize>,
+}
+
+/// Category of a weight system by its polytope
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum WeightSystemCategory {
+    NonIp,
+    NonReflexive,
+    Reflexive,
+}
+
+#[derive(Args)]
+struct ExportArgs {
+    #[arg(long, value_name = "FILE")]
+    parquet_in: PathBuf,
+
+    #[arg(long, value_enum)]
+    format: ExportFormat,
+
+    #[arg(long, value_name = "FILE")]
+    out: PathBuf,
+
+    #[arg(long, value_delimiter = ',')]
+    columns: Option<Vec<String>>,
+
+    #[arg(long)]
+    table: Option<String>,
+
+    #[arg(long)]
+    limit: Option<usize>,
+}
+
+/// Output format of `export`
+#[derive(Clone, Copy, ValueEnum)]
+enum ExportFormat {
+    /// comma-separated values with a header line
+    Csv,
+    /// tab-separated values with a header line
+    Tsv,
+    /// one JSON object per line
+    Jsonl,
+    /// NumPy arrays of the padded vertices, the vertex counts, and the Hodge numbers
+    Npz,
+    /// a table in an SQLite database, which is created if it does not exist
+    Sqlite,
+}
+
+#[derive(Args)]
+struct ImportArgs {
+    #[arg(long = "in", value_name = "FILE")]
+    input: PathBuf,
+
+    #[arg(long, value_enum)]
+    format: ImportFormat,
+
+    #[arg(long, value_name = "FILE")]
+    parquet_out: PathBuf,
+
+    #[arg(long)]
+    index: Option<String>,
+}
+
+/// Input format of `import`
+#[derive(Clone, Copy, ValueEnum)]
+enum ImportFormat {
+    /// comma-separated values with a header line
+    Csv,
+    /// tab-separated values with a header line
+    Tsv,
+    /// one JSON object per line
+    Jsonl,
+}
+
+#[derive(Args)]
+struct FilterArgs {
+    #[arg(long = "where", value_name = "EXPRESSION", allow_hyphen_values = true)]
+    condition: String,
+
+    #[arg(long, value_delimiter = ',')]
+    columns: Option<Vec<String>>,
+
+    #[arg(value_name = "IN")]
+    input: PathBuf,
+
+    #[arg(value_name = "OUT")]
+    output: PathBuf,
+}
+
 fn main() -> Result<()> {
     let args = Cli::parse();
 
     match args.command {
-        Commands::Ipws(args) => ipws::run(args),
-        Commands::Palp(args) => palp::run(args),
+        Commands::Ipws(args) => ipws::run(*args),
+        Commands::Palp(args) => palp::run(*args),
+        Commands::Export(args) => export::run(args),
+        Commands::Import(args) => import::run(args),
+        Commands::Filter(args) => filter::run(args),
     }
 }
@@ -0,0 +1,446 @@
+use anyhow::{bail, Result};
+use std::fs::File;
+use std::io::{BufWriter, Seek, SeekFrom, Write};
+use std::path::Path;
+
+// DOS date of 1980-01-01, the earliest date representable in zip files
+const DATE: u16 = (1 << 5) | 1;
+const VERSION: u16 = 20;
+// the version needed to extract members with Zip64 extra fields
+const ZIP64_VERSION: u16 = 45;
+// sizes and offsets from this value on are stored in Zip64 records
+const ZIP64_LIMIT: u64 = u32::MAX as u64;
+
+/// Encodes the header of an array of 32-bit integers in the `.npy` format, version 1.0.
+fn npy_header(shape: &[usize]) -> Vec<u8> {
+    const MAGIC: &[u8] = b"\x93NUMPY\x01\x00";
+
+    let shape = match shape {
+        [length] => format!("({},)", length),
+        shape => {
+            let lengths: Vec<String> = shape.iter().map(|x| x.to_string()).collect();
+            format!("({})", lengths.join(", "))
+        }
+    };
+    let mut header = format!(
+        "{{'descr': '<i4', 'fortran_order': False, 'shape': {}, }}",
+        shape
+    );
+
+    // the data has to start at a multiple of 64 bytes, and the header ends with a newline
+    let unpadded_length = MAGIC.len() + 2 + header.len() + 1;
+    header += &" ".repeat(unpadded_length.next_multiple_of(64) - unpadded_length);
+    header.push('\n');
+
+    let mut ret = Vec::with_capacity(MAGIC.len() + 2 + header.len());
+    ret.extend_from_slice(MAGIC);
+    ret.extend_from_slice(&(header.len() as u16).to_le_bytes());
+    ret.extend_from_slice(header.as_bytes());
+
+    ret
+}
+
+/// Continues the CRC-32 `crc` of some data with `data`, starting from 0 for no data.
+fn update_crc32(crc: u32, data: &[u8]) -> u32 {
+    use once_cell::sync::Lazy;
+
+    static TABLE: Lazy<[u32; 256]> = Lazy::new(|| {
+        let mut table = [0; 256];
+        for (i, entry) in table.iter_mut().enumerate() {
+            let mut crc = i as u32;
+            for _ in 0..8 {
+                crc = if crc & 1 != 0 {
+                    0xedb88320 ^ (crc >> 1)
+                } else {
+                    crc >> 1
+                };
+            }
+            *entry = crc;
+        }
+        table
+    });
+
+    !data.iter().fold(!crc, |crc, &byte| {
+        TABLE[((crc ^ u32::from(byte)) & 0xff) as usize] ^ (crc >> 8)
+    })
+}
+
+/// The values of an array being written by [`NpzWriter::write_array`], in C order
+pub struct NpyValues<'a> {
+    writer: &'a mut BufWriter<File>,
+    crc: u32,
+    remaining: u64,
+}
+
+impl NpyValues<'_> {
+    pub fn write(&mut self, values: &[i32]) -> Result<()> {
+        if values.len() as u64 > self.remaining {
+            bail!("more values than the shape of the array allows");
+        }
+        self.remaining -= values.len() as u64;
+
+        let bytes: Vec<u8> = values.iter().flat_map(|x| x.to_le_bytes()).collect();
+        self.crc = update_crc32(self.crc, &bytes);
+        self.writer.write_all(&bytes)?;
+
+        Ok(())
+    }
+}
+
+/// Writes an uncompressed zip archive with one `<name>.npy` member per array of 32-bit integers,
+/// as written by `numpy.savez`. The values of each array are streamed to the file, and members
+/// and archives larger than 4 GiB use the Zip64 extensions.
+pub struct NpzWriter {
+    writer: BufWriter<File>,
+    central_directory: Vec<u8>,
+    entry_count: u64,
+    offset: u64,
+}
+
+impl NpzWriter {
+    pub fn create<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Ok(Self {
+            writer: BufWriter::new(File::create(path)?),
+            central_directory: Vec::new(),
+            entry_count: 0,
+            offset: 0,
+        })
+    }
+
+    /// Adds the array `name` of the given shape, whose values are written by `write_values`.
+    pub fn write_array(
+        &mut self,
+        name: &str,
+        shape: &[usize],
+        write_values: impl FnOnce(&mut NpyValues) -> Result<()>,
+    ) -> Result<()> {
+        let name = format!("{}.npy", name);
+        let header = npy_header(shape);
+        let value_count = shape.iter().map(|&x| x as u64).product::<u64>();
+        let size = header.len() as u64 + 4 * value_count;
+
+        let zip64 = size >= ZIP64_LIMIT;
+        let (version, size32) = if zip64 {
+            (ZIP64_VERSION, u32::MAX)
+        } else {
+            (VERSION, size as u32)
+        };
+
+        // fields shared by the local file header and the central directory entry, up to the CRC
+        let mut fields = Vec::new();
+        fields.extend_from_slice(&version.to_le_bytes());
+        fields.extend_from_slice(&0u16.to_le_bytes()); // flags
+        fields.extend_from_slice(&0u16.to_le_bytes()); // stored without compression
+        fields.extend_from_slice(&0u16.to_le_bytes()); // time
+        fields.extend_from_slice(&DATE.to_le_bytes());
+
+        let mut local_extra = Vec::new();
+        if zip64 {
+            local_extra.extend_from_slice(&1u16.to_le_bytes()); // Zip64 extra field
+            local_extra.extend_from_slice(&16u16.to_le_bytes());
+            local_extra.extend_from_slice(&size.to_le_bytes()); // uncompressed size
+            local_extra.extend_from_slice(&size.to_le_bytes()); // compressed size
+        }
+
+        // the CRC is only known after the data is written
+        let crc_offset = self.offset + 4 + fields.len() as u64;
+
+        self.writer.write_all(&0x04034b50u32.to_le_bytes())?;
+        self.writer.write_all(&fields)?;
+        self.writer.write_all(&0u32.to_le_bytes())?; // CRC
+        self.writer.write_all(&size32.to_le_bytes())?; // compressed size
+        self.writer.write_all(&size32.to_le_bytes())?; // uncompressed size
+        self.writer.write_all(&(name.len() as u16).to_le_bytes())?;
+        self.writer
+            .write_all(&(local_extra.len() as u16).to_le_bytes())?;
+        self.writer.write_all(name.as_bytes())?;
+        self.writer.write_all(&local_extra)?;
+        self.writer.write_all(&header)?;
+
+        let mut values = NpyValues {
+            writer: &mut self.writer,
+            crc: update_crc32(0, &header),
+            remaining: value_count,
+        };
+        write_values(&mut values)?;
+        if values.remaining > 0 {
+            bail!("array {} has fewer values than its shape", name);
+        }
+        let crc = values.crc;
+
+        self.writer.seek(SeekFrom::Start(crc_offset))?;
+        self.writer.write_all(&crc.to_le_bytes())?;
+        self.writer.seek(SeekFrom::End(0))?;
+
+        let local_header_offset = self.offset;
+        self.offset += (30 + name.len() + local_extra.len()) as u64 + size;
+
+        // the Zip64 extra field of the central directory holds the values that do not fit in 32 bits
+        let mut extra = Vec::new();
+        if zip64 {
+            extra.extend_from_slice(&size.to_le_bytes());
+            extra.extend_from_slice(&size.to_le_bytes());
+        }
+        let offset32 = if local_header_offset >= ZIP64_LIMIT {
+            extra.extend_from_slice(&local_header_offset.to_le_bytes());
+            u32::MAX
+        } else {
+            local_header_offset as u32
+        };
+        if !extra.is_empty() {
+            let mut field = Vec::new();
+            field.extend_from_slice(&1u16.to_le_bytes());
+            field.extend_from_slice(&(extra.len() as u16).to_le_bytes());
+            field.append(&mut extra);
+            extra = field;
+        }
+
+        let version_made_by = if extra.is_empty() {
+            VERSION
+        } else {
+            ZIP64_VERSION
+        };
+
+        let central_directory = &mut self.central_directory;
+        central_directory.extend_from_slice(&0x02014b50u32.to_le_bytes());
+        central_directory.extend_from_slice(&version_made_by.to_le_bytes());
+        central_directory.extend_from_slice(&version_made_by.to_le_bytes()); // version needed
+        central_directory.extend_from_slice(&fields[2..]);
+        central_directory.extend_from_slice(&crc.to_le_bytes());
+        central_directory.extend_from_slice(&size32.to_le_bytes()); // compressed size
+        central_directory.extend_from_slice(&size32.to_le_bytes()); // uncompressed size
+        central_directory.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        central_directory.extend_from_slice(&(extra.len() as u16).to_le_bytes());
+        central_directory.extend_from_slice(&0u16.to_le_bytes()); // comment length
+        central_directory.extend_from_slice(&0u16.to_le_bytes()); // disk number
+        central_directory.extend_from_slice(&0u16.to_le_bytes()); // internal attributes
+        central_directory.extend_from_slice(&0u32.to_le_bytes()); // external attributes
+        central_directory.extend_from_slice(&offset32.to_le_bytes());
+        central_directory.extend_from_slice(name.as_bytes());
+        central_directory.extend_from_slice(&extra);
+
+        self.entry_count += 1;
+
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> Result<()> {
+        let central_directory_offset = self.offset;
+        let central_directory_size = self.central_directory.len() as u64;
+
+        self.writer.write_all(&self.central_directory)?;
+
+        let zip64 = central_directory_offset >= ZIP64_LIMIT
+            || central_directory_size >= ZIP64_LIMIT
+            || self.entry_count >= u64::from(u16::MAX);
+
+        if zip64 {
+            let end_offset = central_directory_offset + central_directory_size;
+
+            // Zip64 end of central directory record
+            self.writer.write_all(&0x06064b50u32.to_le_bytes())?;
+            self.writer.write_all(&44u64.to_le_bytes())?; // size of the rest of the record
+            self.writer.write_all(&ZIP64_VERSION.to_le_bytes())?; // version made by
+            self.writer.write_all(&ZIP64_VERSION.to_le_bytes())?; // version needed
+            self.writer.write_all(&0u32.to_le_bytes())?; // disk number
+            self.writer.write_all(&0u32.to_le_bytes())?; // disk with the central directory
+            self.writer.write_all(&self.entry_count.to_le_bytes())?;
+            self.writer.write_all(&self.entry_count.to_le_bytes())?;
+            self.writer
+                .write_all(&central_directory_size.to_le_bytes())?;
+            self.writer
+                .write_all(&central_directory_offset.to_le_bytes())?;
+
+            // Zip64 end of central directory locator
+            self.writer.write_all(&0x07064b50u32.to_le_bytes())?;
+            self.writer.write_all(&0u32.to_le_bytes())?; // disk with the Zip64 record
+            self.writer.write_all(&end_offset.to_le_bytes())?;
+            self.writer.write_all(&1u32.to_le_bytes())?; // number of disks
+        }
+
+        let entry_count = u16::try_from(self.entry_count).unwrap_or(u16::MAX);
+        let size32 = u32::try_from(central_directory_size).unwrap_or(u32::MAX);
+        let offset32 = u32::try_from(central_directory_offset).unwrap_or(u32::MAX);
+
+        self.writer.write_all(&0x06054b50u32.to_le_bytes())?;
+        self.writer.write_all(&0u16.to_le_bytes())?; // disk number
+        self.writer.write_all(&0u16.to_le_bytes())?; // disk with the central directory
+        self.writer.write_all(&entry_count.to_le_bytes())?;
+        self.writer.write_all(&entry_count.to_le_bytes())?;
+        self.writer.write_all(&size32.to_le_bytes())?;
+        self.writer.write_all(&offset32.to_le_bytes())?;
+        self.writer.write_all(&0u16.to_le_bytes())?; // comment length
+
+        self.writer.flush()?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn u16_at(data: &[u8], offset: usize) -> u16 {
+        u16::from_le_bytes(data[offset..offset + 2].try_into().unwrap())
+    }
+
+    fn u32_at(data: &[u8], offset: usize) -> u32 {
+        u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap())
+    }
+
+    fn u64_at(data: &[u8], offset: usize) -> u64 {
+        u64::from_le_bytes(data[offset..offset + 8].try_into().unwrap())
+    }
+
+    fn temporary_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("{}-{}", std::process::id(), name))
+    }
+
+    #[test]
+    fn npy_headers() {
+        let header = npy_header(&[2, 3, 4]);
+        assert_eq!(header.len(), 128);
+        assert_eq!(&header[..8], b"\x93NUMPY\x01\x00");
+        assert_eq!(u16_at(&header, 8), 118);
+        let text = std::str::from_utf8(&header[10..]).unwrap();
+        assert_eq!(
+            text.trim_end(),
+            "{'descr': '<i4', 'fortran_order': False, 'shape': (2, 3, 4), }"
+        );
+        assert!(text.ends_with(" \n"));
+
+        let header = npy_header(&[5]);
+        let text = std::str::from_utf8(&header[10..]).unwrap();
+        assert!(text.contains("'shape': (5,)"));
+    }
+
+    #[test]
+    fn crc32() {
+        assert_eq!(update_crc32(0, b"123456789"), 0xcbf43926);
+        assert_eq!(update_crc32(update_crc32(0, b"1234"), b"56789"), 0xcbf43926);
+        assert_eq!(update_crc32(0, b""), 0);
+    }
+
+    #[test]
+    fn zip_layout() {
+        let path = temporary_path("layout.npz");
+        let mut writer = NpzWriter::create(&path).unwrap();
+        writer
+            .write_array("vertices", &[2, 2], |out| {
+                out.write(&[1, 0])?;
+                out.write(&[0, -1])
+            })
+            .unwrap();
+        writer
+            .write_array("vertex_count", &[1], |out| out.write(&[2]))
+            .unwrap();
+        writer.finish().unwrap();
+
+        let data = std::fs::read(&path).unwrap();
+        std::fs::remove_file(path).unwrap();
+
+        // end of central directory record
+        let end = data.len() - 22;
+        assert_eq!(u32_at(&data, end), 0x06054b50);
+        assert_eq!(u16_at(&data, end + 10), 2);
+        let central_directory_size = u32_at(&data, end + 12) as usize;
+        let mut entry = u32_at(&data, end + 16) as usize;
+        assert_eq!(entry + central_directory_size, end);
+
+        let mut names = Vec::new();
+        for _ in 0..2 {
+            assert_eq!(u32_at(&data, entry), 0x02014b50);
+            let crc = u32_at(&data, entry + 16);
+            let size = u32_at(&data, entry + 20) as usize;
+            let name_length = u16_at(&data, entry + 28) as usize;
+            assert_eq!(u16_at(&data, entry + 30), 0);
+            let offset = u32_at(&data, entry + 42) as usize;
+            let name = std::str::from_utf8(&data[entry + 46..entry + 46 + name_length]).unwrap();
+            names.push(name.to_owned());
+
+            // the local file header repeats the CRC and the sizes
+            assert_eq!(u32_at(&data, offset), 0x04034b50);
+            assert_eq!(u32_at(&data, offset + 14), crc);
+            assert_eq!(u32_at(&data, offset + 18), size as u32);
+            assert_eq!(u32_at(&data, offset + 22), size as u32);
+            let start = offset + 30 + name_length;
+            let member = &data[start..start + size];
+            assert_eq!(update_crc32(0, member), crc);
+            assert_eq!(&member[..6], b"\x93NUMPY");
+
+            entry += 46 + name_length;
+        }
+        assert_eq!(names, ["vertices.npy", "vertex_count.npy"]);
+
+        // the values of the first member follow its local file header and the npy header
+        let start = 30 + "vertices.npy".len() + 128;
+        let values = &data[start..start + 16];
+        let values: Vec<i32> = values
+            .chunks(4)
+            .map(|x| i32::from_le_bytes(x.try_into().unwrap()))
+            .collect();
+        assert_eq!(values, [1, 0, 0, -1]);
+    }
+
+    #[test]
+    fn value_counts_match_the_shape() {
+        let path = temporary_path("counts.npz");
+        let mut writer = NpzWriter::create(&path).unwrap();
+
+        let error = writer
+            .write_array("a", &[2], |out| out.write(&[1, 2, 3]))
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "more values than the shape of the array allows"
+        );
+
+        let error = writer
+            .write_array("b", &[2], |out| out.write(&[1]))
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "array b.npy has fewer values than its shape"
+        );
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn zip64_end_records_for_many_members() {
+        let path = temporary_path("many.npz");
+        let count = u16::MAX as usize;
+
+        let mut writer = NpzWriter::create(&path).unwrap();
+        for i in 0..count {
+            writer
+                .write_array(&i.to_string(), &[0], |_| Ok(()))
+                .unwrap();
+        }
+        writer.finish().unwrap();
+
+        let data = std::fs::read(&path).unwrap();
+        std::fs::remove_file(path).unwrap();
+
+        // the entry count does not fit in the end of central directory record
+        let end = data.len() - 22;
+        assert_eq!(u32_at(&data, end), 0x06054b50);
+        assert_eq!(u16_at(&data, end + 10), u16::MAX);
+
+        let locator = end - 20;
+        assert_eq!(u32_at(&data, locator), 0x07064b50);
+        let record = u64_at(&data, locator + 8) as usize;
+        assert_eq!(record, locator - 56);
+
+        assert_eq!(u32_at(&data, record), 0x06064b50);
+        assert_eq!(u64_at(&data, record + 32), count as u64);
+        let central_directory_size = u64_at(&data, record + 40);
+        let central_directory_offset = u64_at(&data, record + 48);
+        assert_eq!(
+            central_directory_offset + central_directory_size,
+            record as u64
+        );
+        assert_eq!(u32_at(&data, central_directory_offset as usize), 0x02014b50);
+    }
+}
@@ -1,27 +1,51 @@
 use std::cmp::{max, min};
-use std::path::Path;
+use std::collections::hash_map::Entry;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs;
+use std::io::Write;
+use std::iter::{self, Enumerate, Peekable};
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use std::{fs, iter};
 
 use anyhow::{bail, Context as _, Result};
+use parquet::file::metadata::KeyValue;
 use regex::Regex;
 
+use crate::import::Records;
+use crate::ipws::check_fourfold_hodge_numbers;
 use crate::parquet_utils::{
-    build_parquet_int_field, build_parquet_int_list_of_lists_field, write_parquet_int_column,
-    write_repeated_parquet_int_column,
+    build_parquet_int_field, build_parquet_int_list_of_lists_field, build_parquet_long_field,
+    build_parquet_optional_int_field, build_parquet_optional_int_list_of_lists_field,
+    list_of_lists_lengths, write_optional_parquet_int_column,
+    write_optional_parquet_int_list_of_lists_column, write_parquet_int_column,
+    write_parquet_int_list_of_lists_column, write_parquet_long_column, ParquetOutput, ParquetTable,
+    RowGroupWriter,
+};
+use crate::polytope::{euler_characteristic, Polytope};
+use crate::{
+    PalpArgs, PalpCheckHodgeArgs, PalpCommands, PalpDedupeArgs, PalpMatrixLayout, PalpVerifyArgs,
 };
-use crate::PalpArgs;
 
 #[derive(Default)]
 struct PolytopeInfo {
     dimension: usize,
     coordinate_list: Vec<i32>,
     vertex_count_list: Vec<i32>,
-    facet_count_list: Vec<i32>,
-    point_count_list: Vec<i32>,
-    dual_point_count_list: Vec<i32>,
-    euler_characteristic_list: Vec<i32>,
-    hodge_number_lists: Vec<Vec<i32>>,
+    facet_count_list: Vec<Option<i32>>,
+    point_count_list: Vec<Option<i32>>,
+    dual_point_count_list: Vec<Option<i32>>,
+    euler_characteristic_list: Vec<Option<i32>>,
+    hodge_number_lists: Vec<Vec<Option<i32>>>,
+    /// the facet normals of each reflexive polytope, which are the vertices of its dual
+    dual_coordinate_list: Option<Vec<i32>>,
+    /// the number of dual vertices of each polytope, or `None` if it is not reflexive
+    dual_vertex_count_list: Vec<Option<i32>>,
+    /// the vertices of the normal form of each polytope
+    normal_form_coordinate_list: Option<Vec<i32>>,
+    /// the number of vertices of each normal form, which leaves out points that are not vertices
+    normal_form_vertex_count_list: Vec<i32>,
+    normal_form_hash_list: Option<Vec<i64>>,
+    layout: Option<PalpLayout>,
 }
 
 impl PolytopeInfo {
@@ -29,17 +53,263 @@ impl PolytopeInfo {
         self.dimension = dimension;
         self.hodge_number_lists.resize(dimension - 2, Vec::new());
     }
+
+    /// The vertices of each polytope.
+    fn vertex_lists(&self) -> impl Iterator<Item = Vec<Vec<i64>>> + '_ {
+        self.split_coordinates(&self.coordinate_list, &self.vertex_count_list)
+    }
+
+    /// Splits a coordinate list into the polytopes, given the number of vertices of each.
+    fn split_coordinates<'a>(
+        &'a self,
+        mut coordinates: &'a [i32],
+        vertex_counts: &'a [i32],
+    ) -> impl Iterator<Item = Vec<Vec<i64>>> + 'a {
+        vertex_counts.iter().map(move |&count| {
+            let (vertices, rest) = coordinates.split_at(count as usize * self.dimension);
+            coordinates = rest;
+
+            vertices
+                .chunks(self.dimension)
+                .map(|v| v.iter().map(|&x| x.into()).collect())
+                .collect()
+        })
+    }
+
+    fn header_format(&self, i: usize) -> HeaderFormat {
+        if self.point_count_list[i].is_none() {
+            HeaderFormat::Plain
+        } else if self.dual_point_count_list[i].is_none() {
+            HeaderFormat::NonReflexive
+        } else if self
+            .hodge_number_lists
+            .first()
+            .is_some_and(|h| h[i].is_some())
+        {
+            HeaderFormat::ReflexiveHodge
+        } else {
+            HeaderFormat::Reflexive
+        }
+    }
+}
+
+/// The header variants written by the PALP programs.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum HeaderFormat {
+    /// `rows cols M:p v N:q f H:h,... [chi]`, as written by `poly.x` for reflexive polytopes
+    ReflexiveHodge,
+    /// `rows cols M:p v N:q f`, reflexive polytopes without Hodge numbers
+    Reflexive,
+    /// `rows cols M:p v F:f`, as written by `poly.x` for non-reflexive polytopes
+    NonReflexive,
+    /// `rows cols`, a bare matrix as used by `class.x` and `cws.x`
+    Plain,
+}
+
+impl HeaderFormat {
+    fn as_str(&self) -> &'static str {
+        match self {
+            HeaderFormat::ReflexiveHodge => "reflexive-hodge",
+            HeaderFormat::Reflexive => "reflexive",
+            HeaderFormat::NonReflexive => "non-reflexive",
+            HeaderFormat::Plain => "plain",
+        }
+    }
+}
+
+/// How a polytope block is laid out in PALP text.
+#[derive(Clone, PartialEq, Eq)]
+struct BlockLayout {
+    /// one vertex per line instead of one coordinate per line
+    vertex_rows: bool,
+    /// the header line with all numbers replaced by `{}`
+    header: String,
+    /// minimum width of a coordinate including its padding
+    width: usize,
+    /// minimum padding in front of the first coordinate of a line
+    first_padding: usize,
+    /// minimum padding in front of the other coordinates of a line
+    padding: usize,
+    /// number of spaces at the end of a coordinate line
+    trailing: usize,
+}
+
+/// Formatting hints to reproduce PALP text byte by byte.
+#[derive(Default)]
+struct PalpLayout {
+    blocks: Vec<BlockLayout>,
+    /// consecutive polytopes sharing a block layout, as `(count, block layout index)`
+    runs: Vec<(usize, usize)>,
+    /// lines outside of polytope blocks, with the number of polytopes preceding them
+    extra_lines: Vec<(usize, String)>,
+    final_newline: bool,
+}
+
+/// The metadata keys of a stored [`PalpLayout`].
+pub const LAYOUT_KEYS: [&str; 4] = [
+    "palp_layouts",
+    "palp_layout_runs",
+    "palp_extra_lines",
+    "palp_final_newline",
+];
+
+/// The metadata key listing the header formats of all polytopes of a file
+pub const HEADER_FORMATS_KEY: &str = "header_formats";
+
+/// Returns whether the PALP text layout of a file with the columns `names` still applies when
+/// only the `kept` columns are written. The headers need the vertices and all the counts, Hodge
+/// numbers and Euler characteristics.
+pub fn keeps_layout(names: &[String], kept: &[String]) -> bool {
+    let is_header_column = |name: &str| {
+        matches!(
+            name,
+            "vertices"
+                | "facet_count"
+                | "point_count"
+                | "dual_point_count"
+                | "euler_characteristic"
+        ) || name.starts_with("h1")
+    };
+
+    names
+        .iter()
+        .all(|x| !is_header_column(x) || kept.contains(x))
+}
+
+impl PalpLayout {
+    fn push(&mut self, block: BlockLayout) {
+        let index = match self.blocks.iter().position(|x| *x == block) {
+            Some(index) => index,
+            None => {
+                self.blocks.push(block);
+                self.blocks.len() - 1
+            }
+        };
+
+        match self.runs.last_mut() {
+            Some((count, i)) if *i == index => *count += 1,
+            _ => self.runs.push((1, index)),
+        }
+    }
+
+    fn last_block(&self) -> Option<&BlockLayout> {
+        self.runs.last().map(|&(_, i)| &self.blocks[i])
+    }
+
+    fn to_metadata(&self) -> Vec<KeyValue> {
+        let blocks: Vec<_> = self
+            .blocks
+            .iter()
+            .map(|x| {
+                format!(
+                    "{} {} {} {} {}\t{}",
+                    if x.vertex_rows { "rows" } else { "columns" },
+                    x.width,
+                    x.first_padding,
+                    x.padding,
+                    x.trailing,
+                    x.header
+                )
+            })
+            .collect();
+
+        let runs: Vec<_> = self
+            .runs
+            .iter()
+            .map(|(count, i)| format!("{}:{}", count, i))
+            .collect();
+
+        let extra_lines: Vec<_> = self
+            .extra_lines
+            .iter()
+            .map(|(pos, line)| format!("{}\t{}", pos, line))
+            .collect();
+
+        let values = [
+            blocks.join("\n"),
+            runs.join(","),
+            extra_lines.join("\n"),
+            self.final_newline.to_string(),
+        ];
+
+        LAYOUT_KEYS
+            .iter()
+            .zip(values)
+            .map(|(key, value)| KeyValue::new(key.to_string(), value))
+            .collect()
+    }
+
+    fn from_metadata(metadata: &[KeyValue]) -> Result<Option<PalpLayout>> {
+        let get = |key: &str| {
+            metadata
+                .iter()
+                .find(|kv| kv.key == key)
+                .map(|kv| kv.value.as_deref().unwrap_or_default())
+        };
+
+        let [Some(blocks), Some(runs), Some(extra_lines), Some(final_newline)] =
+            LAYOUT_KEYS.map(get)
+        else {
+            return Ok(None);
+        };
+
+        let parse_block = |x: &str| -> Option<BlockLayout> {
+            let (numbers, header) = x.split_once('\t')?;
+            let numbers: Vec<_> = numbers.split(' ').collect();
+            let [orientation, width, first_padding, padding, trailing] = numbers[..] else {
+                return None;
+            };
+
+            Some(BlockLayout {
+                vertex_rows: orientation == "rows",
+                header: header.to_owned(),
+                width: width.parse().ok()?,
+                first_padding: first_padding.parse().ok()?,
+                padding: padding.parse().ok()?,
+                trailing: trailing.parse().ok()?,
+            })
+        };
+
+        let parse_run = |x: &str| -> Option<(usize, usize)> {
+            let (count, i) = x.split_once(':')?;
+            Some((count.parse().ok()?, i.parse().ok()?))
+        };
+
+        let parse_extra_line = |x: &str| -> Option<(usize, String)> {
+            let (pos, line) = x.split_once('\t')?;
+            Some((pos.parse().ok()?, line.to_owned()))
+        };
+
+        let layout = (|| {
+            Some(PalpLayout {
+                blocks: blocks.lines().map(parse_block).collect::<Option<_>>()?,
+                runs: runs
+                    .split(',')
+                    .filter(|x| !x.is_empty())
+                    .map(parse_run)
+                    .collect::<Option<_>>()?,
+                extra_lines: extra_lines
+                    .split('\n')
+                    .filter(|x| !x.is_empty())
+                    .map(parse_extra_line)
+                    .collect::<Option<_>>()?,
+                final_newline: final_newline.parse().ok()?,
+            })
+        })();
+
+        layout.map(Some).context("invalid PALP layout metadata")
+    }
 }
 
 struct PalpHeader {
     rows: usize,
     columns: usize,
-    point_count: i32,
-    dual_point_count: i32,
-    vertex_count: i32,
-    facet_count: i32,
-    hodge_numbers: Vec<i32>,
-    euler_characteristic: i32,
+    point_count: Option<i32>,
+    dual_point_count: Option<i32>,
+    vertex_count: Option<i32>,
+    facet_count: Option<i32>,
+    hodge_numbers: Option<Vec<i32>>,
+    euler_characteristic: Option<i32>,
 }
 
 fn parse_header(input: &str) -> Result<PalpHeader> {
@@ -50,12 +320,20 @@ fn parse_header(input: &str) -> Result<PalpHeader> {
             r"(?x)
             ^\s* (?<rows> [0-9]+)
             \s+ (?<columns> [0-9]+)
-            \s+ M: (?<point_count> [0-9]+)
-            \s+ (?<vertex_count> [0-9]+)
-            \s+ N: (?<dual_point_count> [0-9]+)
-            \s+ (?<facet_count> [0-9]+)
-            \s+ H:(?<hodge_numbers> [0-9]+ (, [0-9]+)*)
-            \s+ \[ (?<euler_characteristic> -?[0-9]+) \]",
+            (?:
+                \s+ M: (?<point_count> [0-9]+)
+                \s+ (?<vertex_count> [0-9]+)
+                (?:
+                    \s+ N: (?<dual_point_count> [0-9]+)
+                    \s+ (?<facet_count> [0-9]+)
+                    (?:
+                        \s+ H:(?<hodge_numbers> [0-9]+ (, [0-9]+)*)
+                        (?: \s+ \[ (?<euler_characteristic> -?[0-9]+) \] )?
+                    )?
+                |
+                    \s+ F: (?<non_reflexive_facet_count> [0-9]+)
+                )?
+            )?",
         )
         .unwrap()
     });
@@ -64,341 +342,2158 @@ fn parse_header(input: &str) -> Result<PalpHeader> {
         .captures(input)
         .context(format!("invalid header: {}", input))?;
 
-    let hodge_numbers: Result<Vec<_>, _> =
-        c["hodge_numbers"].split(",").map(|x| x.parse()).collect();
+    // a bare matrix header must not be followed by anything else, while trailing text after the
+    // counts is tolerated
+    if c.name("point_count").is_none() && !input[c[0].len()..].trim().is_empty() {
+        bail!("invalid header: {}", input);
+    }
+
+    let parse = |name: &str| c.name(name).map(|x| x.as_str().parse()).transpose();
+
+    let hodge_numbers = c
+        .name("hodge_numbers")
+        .map(|x| x.as_str().split(',').map(|x| x.parse()).collect())
+        .transpose()?;
 
     Ok(PalpHeader {
         rows: c["rows"].parse()?,
         columns: c["columns"].parse()?,
-        point_count: c["point_count"].parse()?,
-        vertex_count: c["vertex_count"].parse()?,
-        dual_point_count: c["dual_point_count"].parse()?,
-        facet_count: c["facet_count"].parse()?,
-        euler_characteristic: c["euler_characteristic"].parse()?,
-        hodge_numbers: hodge_numbers?,
+        point_count: parse("point_count")?,
+        vertex_count: parse("vertex_count")?,
+        dual_point_count: parse("dual_point_count")?,
+        facet_count: parse("facet_count")?.or(parse("non_reflexive_facet_count")?),
+        euler_characteristic: parse("euler_characteristic")?,
+        hodge_numbers,
     })
 }
 
-fn parse_coordinates(header: &PalpHeader, lines: &mut std::str::Lines) -> Result<Vec<Vec<i32>>> {
+type Lines<'a> = Peekable<Enumerate<std::str::Lines<'a>>>;
+
+/// An invalid polytope block, located by the offending line.
+struct BlockError {
+    line_number: usize,
+    message: &'static str,
+    text: String,
+}
+
+impl BlockError {
+    fn new(line_number: usize, message: &'static str, text: &str) -> BlockError {
+        BlockError {
+            line_number,
+            message,
+            text: text.trim().to_owned(),
+        }
+    }
+}
+
+struct PalpBlock {
+    header: PalpHeader,
+    dimension: usize,
+    vertex_count: usize,
+    coordinates: Vec<i32>,
+}
+
+fn is_header_candidate(line: &str) -> bool {
+    matches!(line.chars().find(|c| !c.is_whitespace()), Some(c) if c.is_numeric())
+}
+
+fn parse_row(line: &str) -> Result<Vec<i32>, std::num::ParseIntError> {
+    line.split(' ')
+        .filter(|x| !x.is_empty())
+        .map(|x| x.parse())
+        .collect()
+}
+
+/// Whether the next line starts a block, which is used to find the next block after an invalid
+/// one. A bare `rows cols` header looks like a row of a 2d polytope, so it only counts if a
+/// well-formed block of the declared shape follows.
+fn starts_block(lines: &Lines) -> bool {
+    let mut lines = lines.clone();
+
+    let Some(header) = lines.next().and_then(|(_, line)| parse_header(line).ok()) else {
+        return false;
+    };
+
+    if header.point_count.is_some() {
+        return true;
+    }
+
+    min(header.rows, header.columns) >= 2
+        && (0..header.rows).all(|_| {
+            lines
+                .next()
+                .is_some_and(|(_, line)| parse_row(line).is_ok_and(|x| x.len() == header.columns))
+        })
+}
+
+fn parse_coordinates<'a>(
+    header: &PalpHeader,
+    header_line_number: usize,
+    lines: &mut Lines<'a>,
+    block: &mut Vec<&'a str>,
+) -> Result<Vec<Vec<i32>>, BlockError> {
     let mut ret = Vec::with_capacity(header.rows);
 
     for _ in 0..header.rows {
-        let line = lines.next().context("incomplete input")?;
-        let values: Result<Vec<i32>, _> = line
-            .split(" ")
-            .filter(|x| !x.is_empty())
-            .map(|x| x.parse())
-            .collect();
-        let values = values?;
+        let Some(&(index, line)) = lines.peek() else {
+            let line_number = header_line_number + block.len();
+            return Err(BlockError::new(line_number, "incomplete input", ""));
+        };
+
+        let error = match parse_row(line) {
+            Ok(values) if values.len() == header.columns => {
+                lines.next();
+                block.push(line);
+                ret.push(values);
+                continue;
+            }
+            Ok(_) => "invalid coordinate count",
+            Err(_) => "invalid coordinate",
+        };
 
-        if values.len() != header.columns {
-            bail!("invalid coordinate count");
+        // a following block means that this one is truncated, which is reported, but the
+        // following block is left for the next call
+        if !starts_block(lines) {
+            lines.next();
+            block.push(line);
         }
 
-        ret.push(values);
+        return Err(BlockError::new(index + 1, error, line));
     }
 
     Ok(ret)
 }
 
-fn parse_palp(input: &str) -> Result<PolytopeInfo> {
-    let mut ret = PolytopeInfo::default();
+fn parse_block<'a>(
+    dimension: usize,
+    lines: &mut Lines<'a>,
+    block: &mut Vec<&'a str>,
+) -> Result<PalpBlock, BlockError> {
+    let (index, line) = lines.next().expect("header line");
+    let line_number = index + 1;
+    block.push(line);
+
+    let header =
+        parse_header(line).map_err(|_| BlockError::new(line_number, "invalid header", line))?;
+
+    let coordinates = parse_coordinates(&header, line_number, lines, block)?;
+    let polytope_dimension = min(header.rows, header.columns);
+    let vertex_count = max(header.rows, header.columns);
+
+    if polytope_dimension < 2 {
+        return Err(BlockError::new(line_number, "invalid dimension", line));
+    }
+
+    if dimension != 0 && dimension != polytope_dimension {
+        return Err(BlockError::new(line_number, "varying dimension", line));
+    }
 
-    let mut lines = input.lines();
+    if let Some(hodge_numbers) = &header.hodge_numbers {
+        if hodge_numbers.len() != polytope_dimension - 2 {
+            return Err(BlockError::new(
+                line_number,
+                "invalid Hodge number count",
+                line,
+            ));
+        }
+    }
 
-    while let Some(line) = lines.next() {
-        match line.chars().find(|c| !c.is_whitespace()) {
-            Some(c) if c.is_numeric() => {}
-            _ => continue,
-        };
+    if header
+        .vertex_count
+        .is_some_and(|v| v as usize != vertex_count)
+    {
+        return Err(BlockError::new(line_number, "invalid vertex count", line));
+    }
 
-        let header = parse_header(line)?;
-        let coordinates = parse_coordinates(&header, &mut lines)?;
-        let dimension = min(header.rows, header.columns);
-        let vertex_count = max(header.rows, header.columns);
+    let mut ret = PalpBlock {
+        header,
+        dimension: polytope_dimension,
+        vertex_count,
+        coordinates: Vec::with_capacity(polytope_dimension * vertex_count),
+    };
 
-        if ret.dimension == 0 {
-            ret.resize(dimension);
-        } else {
-            if ret.dimension != dimension {
-                bail!("varing dimension");
-            }
+    if ret.header.rows < ret.header.columns {
+        for i in 0..vertex_count {
+            ret.coordinates.extend(coordinates.iter().map(|row| row[i]));
+        }
+    } else {
+        for row in &coordinates {
+            ret.coordinates.extend(row);
         }
+    };
 
-        ret.vertex_count_list.push(header.vertex_count);
-        ret.facet_count_list.push(header.facet_count);
-        ret.point_count_list.push(header.point_count);
-        ret.dual_point_count_list.push(header.dual_point_count);
-        ret.euler_characteristic_list
-            .push(header.euler_characteristic);
+    Ok(ret)
+}
 
-        for (i, h) in header.hodge_numbers.into_iter().enumerate() {
-            ret.hodge_number_lists[i].push(h);
-        }
+fn append_polytope(info: &mut PolytopeInfo, polytope: PalpBlock) {
+    let header = polytope.header;
 
-        if header.rows < header.columns {
-            for i in 0..vertex_count {
-                for j in 0..dimension {
-                    ret.coordinate_list.push(coordinates[j][i]);
-                }
+    if info.dimension == 0 {
+        info.resize(polytope.dimension);
+    }
+
+    info.vertex_count_list.push(polytope.vertex_count as i32);
+    info.facet_count_list.push(header.facet_count);
+    info.point_count_list.push(header.point_count);
+    info.dual_point_count_list.push(header.dual_point_count);
+    info.euler_characteristic_list
+        .push(header.euler_characteristic);
+
+    for (i, list) in info.hodge_number_lists.iter_mut().enumerate() {
+        list.push(header.hodge_numbers.as_ref().map(|h| h[i]));
+    }
+
+    info.coordinate_list.extend(polytope.coordinates);
+}
+
+/// Parses PALP output into one `PolytopeInfo` per dimension. Unless `split_dimensions` is set,
+/// all polytopes need to have the same dimension. With `preserve_layout`, formatting hints to
+/// reproduce the input exactly are recorded. Invalid polytopes are copied to `rejects` if
+/// given, and are fatal otherwise.
+fn parse_palp(
+    input: &str,
+    split_dimensions: bool,
+    preserve_layout: bool,
+    mut rejects: Option<&mut String>,
+) -> Result<BTreeMap<usize, PolytopeInfo>> {
+    let mut ret = BTreeMap::new();
+
+    if preserve_layout && split_dimensions {
+        bail!("the layout cannot be preserved when splitting dimensions");
+    }
+
+    if preserve_layout && input.contains('\r') {
+        bail!("the layout of CRLF line endings cannot be preserved");
+    }
+
+    let mut layout = preserve_layout.then(PalpLayout::default);
+
+    let mut lines = input.lines().enumerate().peekable();
+    let mut ordinal = 0;
+    let mut skipped = 0;
+    let mut polytope_count = 0;
+
+    while let Some(&(index, line)) = lines.peek() {
+        if !is_header_candidate(line) {
+            if let Some(layout) = &mut layout {
+                layout.extra_lines.push((polytope_count, line.to_owned()));
             }
-        } else {
-            for i in 0..vertex_count {
-                for j in 0..dimension {
-                    ret.coordinate_list.push(coordinates[i][j]);
+            lines.next();
+            continue;
+        }
+
+        ordinal += 1;
+
+        let dimension = match ret.keys().next() {
+            Some(&dimension) if !split_dimensions => dimension,
+            _ => 0,
+        };
+
+        let mut block = Vec::new();
+        let error = match parse_block(dimension, &mut lines, &mut block) {
+            Ok(polytope) => {
+                let vertex_rows = polytope.header.rows >= polytope.header.columns;
+                let info = ret.entry(polytope.dimension).or_default();
+                append_polytope(info, polytope);
+                polytope_count += 1;
+
+                if let Some(layout) = &mut layout {
+                    let vertex_count = *info.vertex_count_list.last().expect("vertex count");
+                    let coordinate_count = info.dimension * vertex_count as usize;
+                    let coordinates =
+                        &info.coordinate_list[info.coordinate_list.len() - coordinate_count..];
+
+                    let block_layout = detect_block_layout(
+                        info,
+                        vertex_rows,
+                        coordinates,
+                        &block,
+                        layout.last_block(),
+                    )
+                    .with_context(|| {
+                        format!(
+                            "line {} (polytope {}): layout cannot be preserved",
+                            index + 1,
+                            ordinal
+                        )
+                    })?;
+                    layout.push(block_layout);
                 }
+
+                continue;
             }
+            Err(error) => error,
+        };
+
+        let mut message = format!(
+            "line {} (polytope {}): {}",
+            error.line_number, ordinal, error.message
+        );
+        if !error.text.is_empty() {
+            message += &format!(": {}", error.text);
+        }
+
+        let Some(rejects) = rejects.as_deref_mut() else {
+            bail!(message);
         };
 
-        if header.vertex_count as usize != vertex_count {
-            bail!("invalid vertex count");
+        eprintln!("skipping {}", message);
+        skipped += 1;
+
+        // take the remaining lines of the block along, up to the next block
+        while !starts_block(&lines) {
+            let Some((_, line)) = lines.next() else {
+                break;
+            };
+            block.push(line);
+        }
+
+        for line in block {
+            *rejects += line;
+            *rejects += "\n";
         }
     }
 
-    if ret.dimension == 0 {
+    if skipped > 0 {
+        println!("invalid polytopes skipped: {}", skipped);
+    }
+
+    if ret.is_empty() {
         bail!("no polytopes read");
     }
+
+    if let Some(mut layout) = layout {
+        layout.final_newline = input.ends_with('\n');
+        ret.values_mut().next().expect("polytopes").layout = Some(layout);
+    }
+
     Ok(ret)
 }
 
-fn format_palp(info: &PolytopeInfo) -> Result<String> {
-    let mut ret = String::new();
-    let mut coord_index = 0;
-
-    for i in 0..info.vertex_count_list.len() {
-        let hs: Vec<String> = info
-            .hodge_number_lists
-            .iter()
-            .map(|x| x[i].to_string())
-            .collect();
+/// The numbers of the header of polytope `i` in the order they appear in PALP text.
+fn header_values(info: &PolytopeInfo, i: usize, vertex_rows: bool) -> Vec<i32> {
+    let dimension = info.dimension as i32;
+    let vertex_count = info.vertex_count_list[i];
 
-        ret += &format!(
-            "{} {}  M:{} {} N:{} {} H:{} [{}]\n",
-            info.dimension,
-            info.vertex_count_list[i],
-            info.point_count_list[i],
-            info.vertex_count_list[i],
-            info.dual_point_count_list[i],
-            info.facet_count_list[i],
-            hs.join(","),
-            info.euler_characteristic_list[i]
-        );
+    let mut ret = if vertex_rows {
+        vec![vertex_count, dimension]
+    } else {
+        vec![dimension, vertex_count]
+    };
+
+    let Some(point_count) = info.point_count_list[i] else {
+        return ret;
+    };
+    ret.extend([point_count, vertex_count]);
+
+    // headers without a facet count end after the point count
+    let Some(facet_count) = info.facet_count_list[i] else {
+        return ret;
+    };
+    let Some(dual_point_count) = info.dual_point_count_list[i] else {
+        ret.push(facet_count);
+        return ret;
+    };
+    ret.extend([dual_point_count, facet_count]);
+
+    let hodge_numbers: Option<Vec<i32>> = info.hodge_number_lists.iter().map(|x| x[i]).collect();
+    if let Some(hodge_numbers) = hodge_numbers {
+        ret.extend(hodge_numbers);
+        ret.extend(info.euler_characteristic_list[i]);
+    }
 
-        let vertex_count = info.vertex_count_list[i];
-        let coordinates: Vec<_> = info.coordinate_list[coord_index..]
-            .iter()
-            .take(vertex_count as usize * info.dimension as usize)
-            .map(|x| format!("{:5}", x))
-            .collect();
-        coord_index += coordinates.len();
+    ret
+}
 
-        // for i in 0..vertex_count as usize {
-        //     for j in 0..info.dimension {
-        //         ret += &coordinates[i * info.dimension + j];
-        //     }
-        //     ret += "\n";
-        // }
+fn render_template(template: &str, values: &[i32]) -> Option<String> {
+    let mut parts = template.split("{}");
+    let mut values = values.iter();
 
-        for i in 0..info.dimension {
-            for j in 0..vertex_count as usize {
-                ret += &coordinates[j * info.dimension + i];
-            }
-            ret += "\n";
-        }
+    let mut ret = parts.next()?.to_owned();
+    for part in parts {
+        ret += &values.next()?.to_string();
+        ret += part;
     }
 
-    Ok(ret)
+    values.next().is_none().then_some(ret)
 }
 
-fn write_parquet<P: AsRef<Path>>(path: P, info: PolytopeInfo) -> Result<()> {
-    use parquet::basic::{Compression, ZstdLevel};
-    use parquet::file::properties::{WriterProperties, WriterVersion};
-    use parquet::file::writer::SerializedFileWriter;
-    use parquet::schema::types::Type as SchemaType;
+fn header_template(line: &str, values: &[i32]) -> Option<String> {
+    use once_cell::sync::Lazy;
 
-    pub const ROW_GROUP_SIZE: usize = 1_000_000;
+    static RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"-?[0-9]+").unwrap());
 
-    let writer_props = Arc::new(
-        WriterProperties::builder()
-            .set_writer_version(WriterVersion::PARQUET_2_0)
-            .set_compression(Compression::ZSTD(ZstdLevel::try_new(5)?))
-            .build(),
-    );
+    if line.contains(['{', '}']) {
+        return None;
+    }
 
-    let vertices_field = build_parquet_int_list_of_lists_field("vertices")?;
+    let template = RE.replace_all(line, "{}").into_owned();
+    (render_template(&template, values)? == line).then_some(template)
+}
 
-    let mut hodge_number_fields = Vec::new();
-    for i in 0..info.dimension - 2 {
-        hodge_number_fields.push(build_parquet_int_field(&format!("h1{}", i + 1))?);
+fn format_coordinate_line(
+    layout: &BlockLayout,
+    values: impl Iterator<Item = i32>,
+    out: &mut String,
+) {
+    for (i, x) in values.enumerate() {
+        let x = x.to_string();
+        let min_padding = if i == 0 {
+            layout.first_padding
+        } else {
+            layout.padding
+        };
+        let padding = max(layout.width.saturating_sub(x.len()), min_padding);
+
+        out.extend(iter::repeat_n(' ', padding));
+        *out += &x;
     }
 
-    let vertex_count_field = build_parquet_int_field("vertex_count")?;
-    let facet_count_field = build_parquet_int_field("facet_count")?;
-    let point_count_field = build_parquet_int_field("point_count")?;
-    let dual_point_count_field = build_parquet_int_field("dual_point_count")?;
-    let euler_characteristic_field = build_parquet_int_field("euler_characteristic")?;
+    out.extend(iter::repeat_n(' ', layout.trailing));
+    *out += "\n";
+}
 
-    let mut fields = vec![
-        vertices_field,
-        vertex_count_field,
-        facet_count_field,
-        point_count_field,
-        dual_point_count_field,
-    ];
-    fields.append(&mut hodge_number_fields.clone());
-    fields.push(euler_characteristic_field.clone());
+fn format_block(
+    layout: &BlockLayout,
+    info: &PolytopeInfo,
+    i: usize,
+    coordinates: &[i32],
+    out: &mut String,
+) -> Result<()> {
+    let values = header_values(info, i, layout.vertex_rows);
+    *out += &render_template(&layout.header, &values).context("layout does not match header")?;
+    *out += "\n";
+
+    let dimension = info.dimension;
+    if layout.vertex_rows {
+        for vertex in coordinates.chunks(dimension) {
+            format_coordinate_line(layout, vertex.iter().copied(), out);
+        }
+    } else {
+        for j in 0..dimension {
+            let values = coordinates.iter().skip(j).step_by(dimension).copied();
+            format_coordinate_line(layout, values, out);
+        }
+    }
 
-    let schema = SchemaType::group_type_builder("schema")
-        .with_fields(fields)
-        .build()?;
+    Ok(())
+}
 
-    let file = fs::File::create(path)?;
+/// Splits a line into coordinates with the number of spaces in front of them, and the number of
+/// spaces at the end.
+fn split_padded(line: &str) -> Option<(Vec<(usize, &str)>, usize)> {
+    let mut fields = Vec::new();
+    let mut rest = line;
 
-    let row_count = info.vertex_count_list.len();
-    let row_group_count = (row_count + ROW_GROUP_SIZE - 1) / ROW_GROUP_SIZE;
+    loop {
+        let token = rest.trim_start_matches(' ');
+        let padding = rest.len() - token.len();
 
-    let mut writer = SerializedFileWriter::new(file, Arc::new(schema), writer_props.clone())?;
+        if token.is_empty() {
+            return Some((fields, padding));
+        }
 
-    let mut coordinate_end = 0;
+        let end = token.find(' ').unwrap_or(token.len());
+        if token[..end].contains(char::is_whitespace) {
+            return None;
+        }
 
-    for g in 0..row_group_count {
-        let start = g * ROW_GROUP_SIZE;
-        let end = min(start + ROW_GROUP_SIZE, row_count);
+        fields.push((padding, &token[..end]));
+        rest = &token[end..];
+    }
+}
 
-        let mut row_group_writer = writer.next_row_group()?;
+fn detect_line_layout(lines: &[&str]) -> Option<(usize, usize, usize, usize)> {
+    let lines: Vec<_> = lines
+        .iter()
+        .map(|x| split_padded(x))
+        .collect::<Option<_>>()?;
 
-        let coordinate_start = coordinate_end;
-        let mut coordinate_repetition_levels = Vec::new();
-        for &count in &info.vertex_count_list[start..end] {
-            coordinate_end += count as usize * info.dimension;
-            for v in 0..count {
-                for i in 0..info.dimension {
-                    let value = if v == 0 && i == 0 {
-                        0
-                    } else if i == 0 {
-                        1
-                    } else {
-                        2
-                    };
-                    coordinate_repetition_levels.push(value);
-                }
+    let trailing = lines.first()?.1;
+    if lines.iter().any(|x| x.1 != trailing) {
+        return None;
+    }
+
+    let mut widths = BTreeSet::from([0]);
+    let mut first_paddings = BTreeSet::from([0]);
+    let mut paddings = BTreeSet::from([0]);
+
+    for (fields, _) in &lines {
+        for (i, &(padding, token)) in fields.iter().enumerate() {
+            widths.insert(padding + token.len());
+            if i == 0 {
+                first_paddings.insert(padding);
+            } else {
+                paddings.insert(padding);
             }
         }
-        let coordinate_definition_levels = vec![2; coordinate_end - coordinate_start];
-
-        let count = write_repeated_parquet_int_column(
-            &mut row_group_writer,
-            &info.coordinate_list[coordinate_start..coordinate_end],
-            &coordinate_definition_levels,
-            &coordinate_repetition_levels,
-        )?;
-        assert_eq!(count, coordinate_end - coordinate_start);
-
-        write_parquet_int_column(&mut row_group_writer, &info.vertex_count_list[start..end])?;
-        write_parquet_int_column(&mut row_group_writer, &info.facet_count_list[start..end])?;
-        write_parquet_int_column(&mut row_group_writer, &info.point_count_list[start..end])?;
-        write_parquet_int_column(
-            &mut row_group_writer,
-            &info.dual_point_count_list[start..end],
-        )?;
+    }
 
-        for h in &info.hodge_number_lists {
-            write_parquet_int_column(&mut row_group_writer, &h[start..end])?;
+    for &width in &widths {
+        for &first_padding in &first_paddings {
+            for &padding in &paddings {
+                let matches = lines.iter().all(|(fields, _)| {
+                    fields.iter().enumerate().all(|(i, &(p, token))| {
+                        let min_padding = if i == 0 { first_padding } else { padding };
+                        p == max(width.saturating_sub(token.len()), min_padding)
+                    })
+                });
+
+                if matches {
+                    return Some((width, first_padding, padding, trailing));
+                }
+            }
         }
+    }
 
-        write_parquet_int_column(
-            &mut row_group_writer,
-            &info.euler_characteristic_list[start..end],
-        )?;
+    None
+}
 
-        row_group_writer.close()?;
+/// Finds a block layout reproducing `block`, the text of the last polytope added to `info`.
+fn detect_block_layout(
+    info: &PolytopeInfo,
+    vertex_rows: bool,
+    coordinates: &[i32],
+    block: &[&str],
+    previous: Option<&BlockLayout>,
+) -> Option<BlockLayout> {
+    let i = info.vertex_count_list.len() - 1;
+
+    let reproduces_block = |layout: &BlockLayout| {
+        let mut text = String::new();
+        format_block(layout, info, i, coordinates, &mut text).is_ok()
+            && text.lines().eq(block.iter().copied())
+    };
+
+    // most files use a single layout throughout, so try the previous one first
+    if let Some(previous) = previous.filter(|x| x.vertex_rows == vertex_rows) {
+        if reproduces_block(previous) {
+            return Some(previous.clone());
+        }
     }
 
-    writer.close()?;
-
-    Ok(())
+    let header = header_template(block[0], &header_values(info, i, vertex_rows))?;
+    let (width, first_padding, padding, trailing) = detect_line_layout(&block[1..])?;
+
+    let layout = BlockLayout {
+        vertex_rows,
+        header,
+        width,
+        first_padding,
+        padding,
+        trailing,
+    };
+
+    // the widths are detected from the text, which might not be how the numbers are formatted,
+    // as with `01` or `+1`
+    reproduces_block(&layout).then_some(layout)
 }
 
-fn read_parquet<P: AsRef<Path>>(path: P, info: &mut PolytopeInfo) -> Result<()> {
-    use parquet::column::reader::ColumnReader;
-    use parquet::file::reader::FileReader as _;
-    use parquet::file::serialized_reader::SerializedFileReader;
+fn format_header(info: &PolytopeInfo, i: usize, vertex_rows: bool) -> String {
+    let mut ret = if vertex_rows {
+        format!("{} {}", info.vertex_count_list[i], info.dimension)
+    } else {
+        format!("{} {}", info.dimension, info.vertex_count_list[i])
+    };
 
-    let file = fs::File::open(&path)?;
-    let reader = SerializedFileReader::new(file)?;
+    let Some(point_count) = info.point_count_list[i] else {
+        return ret;
+    };
 
-    let metadata = reader.metadata();
+    ret += &format!("  M:{} {}", point_count, info.vertex_count_list[i]);
 
-    let num_columns = metadata.row_group(0).num_columns();
-    info.dimension = num_columns - 4;
+    let Some(facet_count) = info.facet_count_list[i] else {
+        return ret;
+    };
+    let Some(dual_point_count) = info.dual_point_count_list[i] else {
+        ret += &format!(" F:{}", facet_count);
+        return ret;
+    };
 
-    info.resize(info.dimension);
+    ret += &format!(" N:{} {}", dual_point_count, facet_count);
 
-    let mut values = vec![Vec::new(); num_columns];
-    let mut definition_levels = vec![Vec::new(); num_columns];
-    let mut repetition_levels = vec![Vec::new(); num_columns];
-    let mut pos = vec![0; num_columns];
+    let hs: Option<Vec<String>> = info
+        .hodge_number_lists
+        .iter()
+        .map(|x| x[i].map(|h| h.to_string()))
+        .collect();
 
-    for g in 0..metadata.num_row_groups() {
-        let row_group_reader = reader.get_row_group(g)?;
-        let row_group_metadata = metadata.row_group(g);
+    if let Some(hs) = hs.filter(|hs| !hs.is_empty()) {
+        ret += &format!(" H:{}", hs.join(","));
 
-        if num_columns > row_group_metadata.num_columns() {
-            bail!("columns missing");
+        if let Some(euler_characteristic) = info.euler_characteristic_list[i] {
+            ret += &format!(" [{}]", euler_characteristic);
         }
+    }
 
-        for c in 0..num_columns {
-            let c_pos = pos[c];
-            let to_read = row_group_metadata.column(c).num_values() as usize;
-
-            definition_levels[c].extend(iter::repeat(0).take(to_read));
-            repetition_levels[c].extend(iter::repeat(0).take(to_read));
-            values[c].extend(iter::repeat(0).take(to_read));
-
-            let mut column_reader = row_group_reader.get_column_reader(c)?;
-
-            match column_reader {
-                ColumnReader::Int32ColumnReader(ref mut typed_reader) => {
-                    let (_, count, _) = typed_reader.read_records(
-                        to_read,
-                        Some(&mut definition_levels[c][c_pos..c_pos + to_read]),
-                        Some(&mut repetition_levels[c][c_pos..c_pos + to_read]),
-                        &mut values[c][c_pos..c_pos + to_read],
-                    )?;
+    ret
+}
 
-                    assert_eq!(count, to_read);
-                }
-                _ => bail!("invalid Parquet column type"),
+fn format_palp_with_layout(info: &PolytopeInfo, layout: &PalpLayout) -> Result<String> {
+    let mut ret = String::new();
+    let mut extra_lines = layout.extra_lines.iter().peekable();
+    let mut coord_index = 0;
+    let mut i = 0;
+
+    for &(count, block_index) in &layout.runs {
+        let block = layout
+            .blocks
+            .get(block_index)
+            .context("invalid PALP layout")?;
+
+        for _ in 0..count {
+            while let Some((_, line)) = extra_lines.next_if(|(pos, _)| *pos == i) {
+                ret += line;
+                ret += "\n";
             }
 
-            pos[c] += to_read;
+            let coordinate_count = info
+                .vertex_count_list
+                .get(i)
+                .context("PALP layout does not match data")?;
+            let coordinate_end = coord_index + *coordinate_count as usize * info.dimension;
+
+            format_block(
+                block,
+                info,
+                i,
+                &info.coordinate_list[coord_index..coordinate_end],
+                &mut ret,
+            )?;
+
+            coord_index = coordinate_end;
+            i += 1;
         }
     }
 
-    info.coordinate_list = values.remove(0);
-    info.vertex_count_list = values.remove(0);
-    info.facet_count_list = values.remove(0);
-    info.point_count_list = values.remove(0);
-    info.dual_point_count_list = values.remove(0);
-    info.hodge_number_lists = values.drain(0..info.dimension - 2).collect();
-    info.euler_characteristic_list = values.remove(0);
+    if i != info.vertex_count_list.len() {
+        bail!("PALP layout does not match data");
+    }
 
-    Ok(())
+    for (_, line) in extra_lines {
+        ret += line;
+        ret += "\n";
+    }
+
+    if !layout.final_newline {
+        ret.pop();
+    }
+
+    Ok(ret)
 }
 
-pub fn run(args: PalpArgs) -> Result<()> {
-    if let (Some(palp_in), Some(parquet_out)) = (args.palp_in, args.parquet_out) {
-        let input = std::fs::read_to_string(palp_in)?;
-        let polytope_info = parse_palp(&input)?;
-        write_parquet(parquet_out, polytope_info)?;
-    } else if let (Some(palp_out), Some(parquet_in)) = (args.palp_out, args.parquet_in) {
-        let mut polytope_info = PolytopeInfo::default();
-        read_parquet(parquet_in, &mut polytope_info)?;
-        let output = format_palp(&polytope_info)?;
-        std::fs::write(palp_out, output)?;
-    } else {
-        println!("Nothing to do.");
+/// Formats polytopes as PALP text. The layout recorded when reading the PALP text is used unless
+/// a matrix layout is requested.
+fn format_palp(info: &PolytopeInfo, matrix_layout: Option<PalpMatrixLayout>) -> Result<String> {
+    if let (Some(layout), None) = (&info.layout, matrix_layout) {
+        return format_palp_with_layout(info, layout);
     }
 
-    Ok(())
+    let vertex_rows = matches!(matrix_layout, Some(PalpMatrixLayout::Rows));
+
+    let mut ret = String::new();
+    let mut coord_index = 0;
+
+    for i in 0..info.vertex_count_list.len() {
+        ret += &format_header(info, i, vertex_rows);
+        ret += "\n";
+
+        let vertex_count = info.vertex_count_list[i];
+        let coordinates: Vec<_> = info.coordinate_list[coord_index..]
+            .iter()
+            .take(vertex_count as usize * info.dimension)
+            .map(|x| format!("{:5}", x))
+            .collect();
+        coord_index += coordinates.len();
+
+        if vertex_rows {
+            for vertex in coordinates.chunks(info.dimension) {
+                ret += &vertex.concat();
+                ret += "\n";
+            }
+        } else {
+            for i in 0..info.dimension {
+                for j in 0..vertex_count as usize {
+                    ret += &coordinates[j * info.dimension + i];
+                }
+                ret += "\n";
+            }
+        }
+    }
+
+    Ok(ret)
+}
+
+fn write_nullable_int_column<W: Write + Send>(
+    row_group_writer: &mut RowGroupWriter<W>,
+    data: &[Option<i32>],
+    nullable: bool,
+) -> Result<()> {
+    if nullable {
+        write_optional_parquet_int_column(row_group_writer, data)
+    } else {
+        let data: Vec<i32> = data.iter().map(|x| x.expect("value")).collect();
+        write_parquet_int_column(row_group_writer, &data)
+    }
+}
+
+/// Writes imported records to a Parquet file in the schema of `write_parquet`.
+pub fn write_records<P: AsRef<Path>>(records: &Records, path: P) -> Result<()> {
+    let mut info = PolytopeInfo {
+        vertex_count_list: records.int_column("vertex_count")?,
+        ..Default::default()
+    };
+
+    // the dimension is given by the vertices, or by the Hodge numbers h1i if there are none
+    let dimension = match records.element_length("vertices")? {
+        Some(length) => length,
+        None => {
+            (1..)
+                .take_while(|i| records.has_column(&format!("h1{}", i)))
+                .count()
+                + 2
+        }
+    };
+    info.resize(dimension);
+
+    let mut names: Vec<String> = [
+        "vertices",
+        "vertex_count",
+        "facet_count",
+        "point_count",
+        "dual_point_count",
+    ]
+    .map(String::from)
+    .to_vec();
+    for i in 0..dimension - 2 {
+        names.push(format!("h1{}", i + 1));
+    }
+    names.push("euler_characteristic".to_owned());
+
+    info.coordinate_list =
+        records.int_list_of_lists_column("vertices", &info.vertex_count_list, dimension)?;
+    info.facet_count_list = records.optional_int_column("facet_count")?;
+    info.point_count_list = records.optional_int_column("point_count")?;
+    info.dual_point_count_list = records.optional_int_column("dual_point_count")?;
+    info.hodge_number_lists = (0..dimension - 2)
+        .map(|i| records.optional_int_column(&format!("h1{}", i + 1)))
+        .collect::<Result<_>>()?;
+    info.euler_characteristic_list = records.optional_int_column("euler_characteristic")?;
+
+    if records.has_column("dual_vertices") {
+        let (dual_coordinates, dual_vertex_counts) =
+            records.optional_int_list_of_lists_column("dual_vertices", dimension)?;
+
+        for (i, (&facet_count, &dual_vertex_count)) in info
+            .facet_count_list
+            .iter()
+            .zip(&dual_vertex_counts)
+            .enumerate()
+        {
+            if dual_vertex_count.is_some() && facet_count != dual_vertex_count {
+                bail!("record {}: dual_vertices does not match facet_count", i + 1);
+            }
+        }
+
+        info.dual_coordinate_list = Some(dual_coordinates);
+        info.dual_vertex_count_list = dual_vertex_counts;
+        names.push("dual_vertices".to_owned());
+    }
+    if records.has_column("normal_form") {
+        let (normal_form_coordinates, normal_form_vertex_counts) =
+            records.optional_int_list_of_lists_column("normal_form", dimension)?;
+
+        info.normal_form_coordinate_list = Some(normal_form_coordinates);
+        info.normal_form_vertex_count_list = normal_form_vertex_counts
+            .into_iter()
+            .enumerate()
+            .map(|(i, count)| {
+                count.with_context(|| format!("record {}: normal_form missing", i + 1))
+            })
+            .collect::<Result<_>>()?;
+        names.push("normal_form".to_owned());
+    }
+    if records.has_column("normal_form_hash") {
+        info.normal_form_hash_list = Some(records.long_column("normal_form_hash")?);
+        names.push("normal_form_hash".to_owned());
+    }
+
+    records.check_columns(&names)?;
+
+    println!("dimension: {}", dimension);
+    write_parquet(ParquetOutput::parquet(fs::File::create(path)?), &info, None)
+}
+
+/// Writes the polytopes, only with the selected `columns` if given. The PALP text layout is only
+/// stored together with the vertices and all the columns of the headers.
+fn write_parquet<W: Write + Send>(
+    output: ParquetOutput<W>,
+    info: &PolytopeInfo,
+    columns: Option<&[String]>,
+) -> Result<()> {
+    use parquet::basic::{Compression, ZstdLevel};
+    use parquet::file::properties::{WriterProperties, WriterVersion};
+    use parquet::schema::types::Type as SchemaType;
+
+    pub const ROW_GROUP_SIZE: usize = 1_000_000;
+
+    let writer_props = Arc::new(
+        WriterProperties::builder()
+            .set_writer_version(WriterVersion::PARQUET_2_0)
+            .set_compression(Compression::ZSTD(ZstdLevel::try_new(5)?))
+            .build(),
+    );
+
+    // columns are only declared optional if a header format without the quantity was read,
+    // so that files converted from complete headers keep their schema
+    let mut nullable_columns = vec![
+        &info.facet_count_list,
+        &info.point_count_list,
+        &info.dual_point_count_list,
+    ];
+    nullable_columns.extend(&info.hodge_number_lists);
+    nullable_columns.push(&info.euler_characteristic_list);
+
+    let nullable: Vec<bool> = nullable_columns
+        .iter()
+        .map(|x| x.iter().any(Option::is_none))
+        .collect();
+
+    let mut nullable_names = vec![
+        "facet_count".to_owned(),
+        "point_count".to_owned(),
+        "dual_point_count".to_owned(),
+    ];
+    for i in 0..info.dimension - 2 {
+        nullable_names.push(format!("h1{}", i + 1));
+    }
+    nullable_names.push("euler_characteristic".to_owned());
+
+    let mut fields = vec![
+        build_parquet_int_list_of_lists_field("vertices")?,
+        build_parquet_int_field("vertex_count")?,
+    ];
+    for (name, &nullable) in nullable_names.iter().zip(&nullable) {
+        fields.push(if nullable {
+            build_parquet_optional_int_field(name)?
+        } else {
+            build_parquet_int_field(name)?
+        });
+    }
+    // like the columns above, the dual vertices are only optional if there are nulls
+    let dual_nullable = info.dual_vertex_count_list.iter().any(Option::is_none);
+    if info.dual_coordinate_list.is_some() {
+        fields.push(if dual_nullable {
+            build_parquet_optional_int_list_of_lists_field("dual_vertices")?
+        } else {
+            build_parquet_int_list_of_lists_field("dual_vertices")?
+        });
+    }
+    if info.normal_form_coordinate_list.is_some() {
+        fields.push(build_parquet_int_list_of_lists_field("normal_form")?);
+    }
+    if info.normal_form_hash_list.is_some() {
+        fields.push(build_parquet_long_field("normal_form_hash")?);
+    }
+
+    let selected = |name: &str| columns.is_none_or(|columns| columns.iter().any(|x| x == name));
+    for name in columns.unwrap_or_default() {
+        if !fields.iter().any(|f| f.name() == name) {
+            bail!("unknown column {}", name);
+        }
+    }
+    let names: Vec<String> = fields.iter().map(|f| f.name().to_owned()).collect();
+    fields.retain(|f| selected(f.name()));
+    let kept: Vec<String> = fields.iter().map(|f| f.name().to_owned()).collect();
+    let keep_layout = keeps_layout(&names, &kept);
+
+    let schema = SchemaType::group_type_builder("schema")
+        .with_fields(fields)
+        .build()?;
+
+    let row_count = info.vertex_count_list.len();
+    let row_group_count = row_count.div_ceil(ROW_GROUP_SIZE);
+
+    let header_formats: BTreeSet<_> = (0..row_count).map(|i| info.header_format(i)).collect();
+    let header_formats: Vec<_> = header_formats.iter().map(|x| x.as_str()).collect();
+
+    let mut writer = output.writer(Arc::new(schema), writer_props.clone())?;
+
+    writer.append_key_value_metadata(KeyValue::new(
+        "dimension".to_owned(),
+        info.dimension.to_string(),
+    ));
+    writer.append_key_value_metadata(KeyValue::new(
+        HEADER_FORMATS_KEY.to_owned(),
+        header_formats.join(","),
+    ));
+
+    if let (Some(layout), true) = (&info.layout, keep_layout) {
+        for kv in layout.to_metadata() {
+            writer.append_key_value_metadata(kv);
+        }
+    }
+
+    let mut coordinate_end = 0;
+    let mut dual_coordinate_end = 0;
+    let mut normal_form_coordinate_end = 0;
+
+    for g in 0..row_group_count {
+        let start = g * ROW_GROUP_SIZE;
+        let end = min(start + ROW_GROUP_SIZE, row_count);
+
+        let mut row_group_writer = writer.next_row_group()?;
+
+        if selected("vertices") {
+            let coordinate_start = coordinate_end;
+            coordinate_end += info.vertex_count_list[start..end]
+                .iter()
+                .map(|&count| count as usize * info.dimension)
+                .sum::<usize>();
+
+            write_parquet_int_list_of_lists_column(
+                &mut row_group_writer,
+                &info.coordinate_list[coordinate_start..coordinate_end],
+                &info.vertex_count_list[start..end],
+                info.dimension,
+            )?;
+        }
+
+        if selected("vertex_count") {
+            write_parquet_int_column(&mut row_group_writer, &info.vertex_count_list[start..end])?;
+        }
+
+        for ((column, &nullable), name) in
+            nullable_columns.iter().zip(&nullable).zip(&nullable_names)
+        {
+            if selected(name) {
+                write_nullable_int_column(&mut row_group_writer, &column[start..end], nullable)?;
+            }
+        }
+
+        if let (Some(dual_coordinate_list), true) =
+            (&info.dual_coordinate_list, selected("dual_vertices"))
+        {
+            let dual_vertex_counts = &info.dual_vertex_count_list[start..end];
+
+            let dual_coordinate_start = dual_coordinate_end;
+            dual_coordinate_end += dual_vertex_counts
+                .iter()
+                .map(|&count| count.unwrap_or(0) as usize * info.dimension)
+                .sum::<usize>();
+            let dual_coordinates =
+                &dual_coordinate_list[dual_coordinate_start..dual_coordinate_end];
+
+            if dual_nullable {
+                write_optional_parquet_int_list_of_lists_column(
+                    &mut row_group_writer,
+                    dual_coordinates,
+                    dual_vertex_counts,
+                    info.dimension,
+                )?;
+            } else {
+                let dual_vertex_counts: Vec<i32> =
+                    dual_vertex_counts.iter().flatten().copied().collect();
+                write_parquet_int_list_of_lists_column(
+                    &mut row_group_writer,
+                    dual_coordinates,
+                    &dual_vertex_counts,
+                    info.dimension,
+                )?;
+            }
+        }
+
+        if let (Some(normal_form_coordinate_list), true) =
+            (&info.normal_form_coordinate_list, selected("normal_form"))
+        {
+            let normal_form_vertex_counts = &info.normal_form_vertex_count_list[start..end];
+
+            let normal_form_coordinate_start = normal_form_coordinate_end;
+            normal_form_coordinate_end += normal_form_vertex_counts
+                .iter()
+                .map(|&count| count as usize * info.dimension)
+                .sum::<usize>();
+
+            write_parquet_int_list_of_lists_column(
+                &mut row_group_writer,
+                &normal_form_coordinate_list
+                    [normal_form_coordinate_start..normal_form_coordinate_end],
+                normal_form_vertex_counts,
+                info.dimension,
+            )?;
+        }
+
+        if let (Some(normal_form_hash_list), true) =
+            (&info.normal_form_hash_list, selected("normal_form_hash"))
+        {
+            write_parquet_long_column(&mut row_group_writer, &normal_form_hash_list[start..end])?;
+        }
+
+        row_group_writer.close()?;
+    }
+
+    writer.close()?;
+
+    Ok(())
+}
+
+/// Reads a Parquet file written by `write_parquet`. Only `vertex_count` and the columns accepted
+/// by `is_selected` are decoded; the others are null or left empty.
+fn read_parquet<P: AsRef<Path>>(
+    path: P,
+    info: &mut PolytopeInfo,
+    is_selected: impl Fn(&str) -> bool,
+) -> Result<()> {
+    use parquet::column::reader::ColumnReader;
+    use parquet::file::reader::FileReader as _;
+    use parquet::file::serialized_reader::SerializedFileReader;
+
+    let file = fs::File::open(&path)?;
+    let reader = SerializedFileReader::new(file)?;
+
+    let metadata = reader.metadata();
+    let schema = metadata.file_metadata().schema_descr();
+    let num_columns = schema.num_columns();
+
+    let names: Vec<String> = (0..num_columns)
+        .map(|c| schema.column(c).path().parts()[0].clone())
+        .collect();
+
+    let mut dimension = None;
+    if let Some(kv_metadata) = metadata.file_metadata().key_value_metadata() {
+        info.layout = PalpLayout::from_metadata(kv_metadata)?;
+
+        if let Some(value) = kv_metadata.iter().find(|kv| kv.key == "dimension") {
+            let value = value.value.as_deref().context("dimension missing")?;
+            dimension = Some(value.parse().context("invalid dimension")?);
+        }
+    }
+
+    // files written before the dimension was stored have one column per Hodge number h1i
+    info.dimension = match dimension {
+        Some(dimension) => dimension,
+        None => names.iter().filter(|x| x.starts_with("h1")).count() + 2,
+    };
+    info.resize(info.dimension);
+
+    let mut coordinates = Vec::new();
+    let mut vertex_counts = Vec::new();
+    let mut dual_coordinates = Vec::new();
+    let mut dual_vertex_counts = Vec::new();
+    let mut normal_form_coordinates = Vec::new();
+    let mut normal_form_vertex_counts = Vec::new();
+    let mut normal_form_hashes = Vec::new();
+    let mut values = vec![Vec::new(); num_columns];
+
+    // the vertex counts are always needed, from the vertices if the file has no vertex_count
+    let has_vertex_count = names.iter().any(|x| x == "vertex_count");
+    let is_decoded = |name: &str| {
+        name == "vertex_count" || (name == "vertices" && !has_vertex_count) || is_selected(name)
+    };
+
+    for g in 0..metadata.num_row_groups() {
+        let row_group_reader = reader.get_row_group(g)?;
+        let row_group_metadata = metadata.row_group(g);
+
+        if num_columns > row_group_metadata.num_columns() {
+            bail!("columns missing");
+        }
+
+        for c in 0..num_columns {
+            if !is_decoded(&names[c]) {
+                continue;
+            }
+
+            let to_read = row_group_metadata.column(c).num_values() as usize;
+
+            let mut column_values = vec![0; to_read];
+            let mut definition_levels = vec![0; to_read];
+            let mut repetition_levels = vec![0; to_read];
+
+            let mut column_reader = row_group_reader.get_column_reader(c)?;
+
+            match column_reader {
+                ColumnReader::Int32ColumnReader(ref mut typed_reader) => {
+                    let (_, count, level_count) = typed_reader.read_records(
+                        to_read,
+                        Some(&mut definition_levels),
+                        Some(&mut repetition_levels),
+                        &mut column_values,
+                    )?;
+
+                    assert_eq!(level_count, to_read);
+                    column_values.truncate(count);
+                }
+                ColumnReader::Int64ColumnReader(ref mut typed_reader)
+                    if names[c] == "normal_form_hash" =>
+                {
+                    let mut hashes = vec![0; to_read];
+                    let (count, _, _) =
+                        typed_reader.read_records(to_read, None, None, &mut hashes)?;
+                    hashes.truncate(count);
+                    normal_form_hashes.append(&mut hashes);
+                    continue;
+                }
+                _ => bail!("invalid Parquet column type"),
+            }
+
+            match names[c].as_str() {
+                "vertices" => {
+                    coordinates.append(&mut column_values);
+                    vertex_counts.extend(list_of_lists_lengths(
+                        &definition_levels,
+                        &repetition_levels,
+                        schema.column(c).max_def_level(),
+                    ));
+                }
+                "dual_vertices" => {
+                    dual_coordinates.append(&mut column_values);
+                    dual_vertex_counts.extend(list_of_lists_lengths(
+                        &definition_levels,
+                        &repetition_levels,
+                        schema.column(c).max_def_level(),
+                    ));
+                }
+                "normal_form" => {
+                    normal_form_coordinates.append(&mut column_values);
+                    for count in list_of_lists_lengths(
+                        &definition_levels,
+                        &repetition_levels,
+                        schema.column(c).max_def_level(),
+                    ) {
+                        normal_form_vertex_counts.push(count.context("missing normal form")?);
+                    }
+                }
+                _ => {
+                    let max_definition_level = schema.column(c).max_def_level();
+                    let mut column_values = column_values.into_iter();
+
+                    values[c].extend(definition_levels.iter().map(|&d| {
+                        if d == max_definition_level {
+                            column_values.next()
+                        } else {
+                            None
+                        }
+                    }));
+                }
+            }
+        }
+    }
+
+    let has_column = |name: &str| names.iter().any(|x| x == name);
+
+    // the vertices cannot be left empty for callers that need them
+    if is_selected("vertices") && !has_column("vertices") {
+        bail!("column vertices missing");
+    }
+
+    // files written with a column selection may only have one of the vertex counts and the
+    // vertices, and the columns they lack are null
+    info.vertex_count_list = match names.iter().position(|x| x == "vertex_count") {
+        Some(c) => std::mem::take(&mut values[c]),
+        None if has_column("vertices") => vertex_counts,
+        None => bail!("column vertex_count missing"),
+    }
+    .into_iter()
+    .map(|x| x.context("missing vertex count"))
+    .collect::<Result<_>>()?;
+    info.coordinate_list = coordinates;
+
+    let row_count = info.vertex_count_list.len();
+    let mut take_column = |name: &str| -> Result<Vec<Option<i32>>> {
+        let mut column = match names.iter().position(|x| x == name) {
+            Some(c) => std::mem::take(&mut values[c]),
+            None => Vec::new(),
+        };
+        column.resize(row_count, None);
+        Ok(column)
+    };
+
+    info.facet_count_list = take_column("facet_count")?;
+    info.point_count_list = take_column("point_count")?;
+    info.dual_point_count_list = take_column("dual_point_count")?;
+    info.hodge_number_lists = (0..info.dimension - 2)
+        .map(|i| take_column(&format!("h1{}", i + 1)))
+        .collect::<Result<_>>()?;
+    info.euler_characteristic_list = take_column("euler_characteristic")?;
+
+    let has_column = |name: &str| has_column(name) && is_selected(name);
+    if has_column("dual_vertices") {
+        info.dual_coordinate_list = Some(dual_coordinates);
+        info.dual_vertex_count_list = dual_vertex_counts;
+    }
+    if has_column("normal_form") {
+        info.normal_form_coordinate_list = Some(normal_form_coordinates);
+        info.normal_form_vertex_count_list = normal_form_vertex_counts;
+    }
+    if has_column("normal_form_hash") {
+        info.normal_form_hash_list = Some(normal_form_hashes);
+    }
+
+    Ok(())
+}
+
+/// Reads the polytopes of a PALP or Parquet file, one `PolytopeInfo` per dimension. Of Parquet
+/// files, only the columns accepted by `is_selected` are read.
+fn read_polytope_info(
+    palp_in: Option<PathBuf>,
+    parquet_in: Option<PathBuf>,
+    is_selected: impl Fn(&str) -> bool,
+) -> Result<BTreeMap<usize, PolytopeInfo>> {
+    if let Some(palp_in) = palp_in {
+        let input = std::fs::read_to_string(palp_in)?;
+        parse_palp(&input, true, false, None)
+    } else if let Some(parquet_in) = parquet_in {
+        let mut polytope_info = PolytopeInfo::default();
+        read_parquet(parquet_in, &mut polytope_info, is_selected)?;
+        Ok(BTreeMap::from([(polytope_info.dimension, polytope_info)]))
+    } else {
+        bail!("no input given");
+    }
+}
+
+/// Writes the first `limit` polytopes as polymake objects in JSON format to `<n>.poly` in `dir`,
+/// with the vertices in homogeneous coordinates and the counts known from the header.
+fn write_polymake<P: AsRef<Path>>(info: &PolytopeInfo, dir: P, limit: usize) -> Result<()> {
+    use serde_json::{json, Map, Value};
+
+    fs::create_dir_all(&dir)?;
+
+    for (i, vertices) in info.vertex_lists().take(limit).enumerate() {
+        // polymake stores the coordinates of rational polytopes as strings
+        let vertices: Vec<Vec<String>> = vertices
+            .iter()
+            .map(|vertex| {
+                iter::once("1".to_owned())
+                    .chain(vertex.iter().map(|x| x.to_string()))
+                    .collect()
+            })
+            .collect();
+
+        let mut object = Map::new();
+        object.insert(
+            "_ns".to_owned(),
+            json!({ "polymake": ["https://polymake.org", "4.0"] }),
+        );
+        object.insert("_type".to_owned(), json!("polytope::Polytope<Rational>"));
+        object.insert("CONE_AMBIENT_DIM".to_owned(), json!(info.dimension + 1));
+        object.insert("LATTICE".to_owned(), json!(true));
+        object.insert("N_VERTICES".to_owned(), json!(vertices.len()));
+        object.insert("VERTICES".to_owned(), json!(vertices));
+
+        let counts = [
+            ("N_FACETS", info.facet_count_list[i]),
+            ("N_LATTICE_POINTS", info.point_count_list[i]),
+        ];
+        for (name, count) in counts {
+            if let Some(count) = count {
+                object.insert(name.to_owned(), json!(count));
+            }
+        }
+        // PALP headers only have N: counts for reflexive polytopes
+        if info.dual_point_count_list[i].is_some() {
+            object.insert("REFLEXIVE".to_owned(), json!(true));
+        }
+
+        let path = dir.as_ref().join(format!("{}.poly", i + 1));
+        fs::write(path, serde_json::to_string(&Value::Object(object))? + "\n")?;
+    }
+
+    println!("polytopes written: {}", limit);
+
+    Ok(())
+}
+
+/// Writes the vertices of the first `limit` polytopes as a Python list, followed by a line
+/// creating a `LatticePolytope` for each of them when loaded in Sage.
+fn write_sage<P: AsRef<Path>>(info: &PolytopeInfo, path: P, limit: usize) -> Result<()> {
+    let mut output = String::from("vertex_lists = [\n");
+
+    for vertices in info.vertex_lists().take(limit) {
+        let vertices: Vec<String> = vertices
+            .iter()
+            .map(|vertex| {
+                let coordinates: Vec<String> = vertex.iter().map(|x| x.to_string()).collect();
+                format!("[{}]", coordinates.join(", "))
+            })
+            .collect();
+        output += &format!("    [{}],\n", vertices.join(", "));
+    }
+
+    output += "]\n\npolytopes = [LatticePolytope(vertices) for vertices in vertex_lists]\n";
+
+    fs::write(path, output)?;
+
+    println!("polytopes written: {}", limit);
+
+    Ok(())
+}
+
+/// Names polytope `i` of `info` in messages, with its dimension if the input has several.
+fn polytope_label(info: &PolytopeInfo, i: usize, several_dimensions: bool) -> String {
+    if several_dimensions {
+        format!("{}d polytope {}", info.dimension, i + 1)
+    } else {
+        format!("polytope {}", i + 1)
+    }
+}
+
+fn report_mismatch(mismatches: &mut usize, label: &str, name: &str, expected: i32, computed: i32) {
+    if expected != computed {
+        println!(
+            "{}: {} expected {}, computed {}",
+            label, name, expected, computed
+        );
+        *mismatches += 1;
+    }
+}
+
+fn verify_counts(args: PalpVerifyArgs) -> Result<()> {
+    let infos = read_polytope_info(args.palp_in, args.parquet_in, |name| {
+        matches!(name, "vertices" | "point_count" | "dual_point_count")
+    })?;
+    let several_dimensions = infos.len() > 1;
+    let mut remaining = args.limit.unwrap_or(usize::MAX);
+
+    let mut mismatches = 0;
+    let mut count = 0;
+
+    for info in infos.values() {
+        let limit = min(remaining, info.vertex_count_list.len());
+        remaining -= limit;
+
+        for (i, vertices) in info.vertex_lists().enumerate().take(limit) {
+            let label = polytope_label(info, i, several_dimensions);
+            let polytope = Polytope::new(&vertices).with_context(|| label.clone())?;
+
+            if let Some(point_count) = info.point_count_list[i] {
+                let computed = polytope.lattice_points().len() as i32;
+                report_mismatch(&mut mismatches, &label, "M:", point_count, computed);
+            }
+
+            if let Some(dual_point_count) = info.dual_point_count_list[i] {
+                match polytope.dual() {
+                    Ok(dual) => {
+                        let computed = dual.lattice_points().len() as i32;
+                        report_mismatch(&mut mismatches, &label, "N:", dual_point_count, computed);
+                    }
+                    Err(e) => {
+                        println!("{}: N: {}", label, e);
+                        mismatches += 1;
+                    }
+                }
+            }
+
+            count += 1;
+        }
+    }
+
+    println!("polytopes checked: {}", count);
+    println!("mismatches: {}", mismatches);
+
+    if mismatches > 0 {
+        bail!("point counts do not match");
+    }
+
+    Ok(())
+}
+
+fn verify_hodge(args: PalpVerifyArgs) -> Result<()> {
+    let infos = read_polytope_info(args.palp_in, args.parquet_in, |name| {
+        name == "vertices" || name.starts_with("h1") || name == "euler_characteristic"
+    })?;
+    let several_dimensions = infos.len() > 1;
+    let mut remaining = args.limit.unwrap_or(usize::MAX);
+
+    let mut mismatches = 0;
+    let mut count = 0;
+    let mut skipped = 0;
+
+    for info in infos.values() {
+        let limit = min(remaining, info.vertex_count_list.len());
+        remaining -= limit;
+
+        for (i, vertices) in info.vertex_lists().enumerate().take(limit) {
+            if info.hodge_number_lists.iter().all(|x| x[i].is_none()) {
+                continue;
+            }
+
+            let label = polytope_label(info, i, several_dimensions);
+            let polytope = Polytope::new(&vertices).with_context(|| label.clone())?;
+            let Some(hodge_numbers) = polytope.hodge_numbers().with_context(|| label.clone())?
+            else {
+                skipped += 1;
+                continue;
+            };
+
+            for (k, (list, &computed)) in info
+                .hodge_number_lists
+                .iter()
+                .zip(&hodge_numbers)
+                .enumerate()
+            {
+                if let Some(expected) = list[i] {
+                    let name = format!("h1{}", k + 1);
+                    report_mismatch(&mut mismatches, &label, &name, expected, computed as i32);
+                }
+            }
+
+            if let (Some(expected), Some(computed)) = (
+                info.euler_characteristic_list[i],
+                euler_characteristic(&hodge_numbers),
+            ) {
+                report_mismatch(&mut mismatches, &label, "chi", expected, computed as i32);
+            }
+
+            count += 1;
+        }
+    }
+
+    println!("polytopes checked: {}", count);
+    if skipped > 0 {
+        println!(
+            "polytopes skipped, only 4d and 5d are supported: {}",
+            skipped
+        );
+    }
+    println!("mismatches: {}", mismatches);
+
+    if mismatches > 0 {
+        bail!("Hodge numbers do not match");
+    }
+
+    Ok(())
+}
+
+fn check_hodge(args: PalpCheckHodgeArgs) -> Result<()> {
+    let infos = read_polytope_info(args.palp_in, args.parquet_in, |name| {
+        name.starts_with("h1") || name == "euler_characteristic"
+    })?;
+    let several_dimensions = infos.len() > 1;
+    let mut remaining = args.limit.unwrap_or(usize::MAX);
+
+    let mut violations = 0;
+    let mut count = 0;
+    let mut skipped = 0;
+
+    for info in infos.values() {
+        let limit = min(remaining, info.vertex_count_list.len());
+        remaining -= limit;
+
+        for i in 0..limit {
+            let Some(hodge_numbers) = info
+                .hodge_number_lists
+                .iter()
+                .map(|x| x[i])
+                .collect::<Option<Vec<_>>>()
+            else {
+                continue;
+            };
+            let euler = info.euler_characteristic_list[i];
+
+            let messages = match *hodge_numbers.as_slice() {
+                [h11, h12, h13] => check_fourfold_hodge_numbers([h11, h12, h13], euler, None),
+                _ => {
+                    let hodge_numbers: Vec<i64> = hodge_numbers.iter().map(|&h| h.into()).collect();
+                    let Some(computed_euler) = euler_characteristic(&hodge_numbers) else {
+                        skipped += 1;
+                        continue;
+                    };
+
+                    let mut messages = Vec::new();
+                    for (k, h) in hodge_numbers.iter().enumerate() {
+                        if *h < 0 {
+                            messages.push(format!("h1{} = {} is negative", k + 1, h));
+                        }
+                    }
+                    match euler {
+                        Some(euler) if i64::from(euler) != computed_euler => {
+                            messages.push(format!(
+                                "Euler characteristic {} differs from {}",
+                                euler, computed_euler
+                            ))
+                        }
+                        _ => {}
+                    }
+                    messages
+                }
+            };
+
+            for message in messages {
+                println!(
+                    "{}: {}",
+                    polytope_label(info, i, several_dimensions),
+                    message
+                );
+                violations += 1;
+            }
+
+            count += 1;
+        }
+    }
+
+    println!("polytopes checked: {}", count);
+    if skipped > 0 {
+        println!(
+            "polytopes skipped, only 4d and 5d are supported: {}",
+            skipped
+        );
+    }
+    println!("violations: {}", violations);
+
+    if violations > 0 {
+        bail!("Hodge numbers are inconsistent");
+    }
+
+    Ok(())
+}
+
+/// Computes the quantities missing from the headers. Point and facet counts are filled in for all
+/// polytopes, the dual point count for reflexive polytopes, and the Hodge numbers for reflexive
+/// polytopes in four and five dimensions.
+fn fill_header(info: &mut PolytopeInfo) -> Result<()> {
+    let vertex_lists: Vec<_> = info.vertex_lists().collect();
+
+    let mut ip_count = 0;
+    let mut reflexive_count = 0;
+
+    for (i, vertices) in vertex_lists.into_iter().enumerate() {
+        let polytope = Polytope::new(&vertices).with_context(|| format!("polytope {}", i + 1))?;
+        if polytope.vertices.len() != vertices.len() {
+            bail!(
+                "polytope {}: only {} of {} points are vertices",
+                i + 1,
+                polytope.vertices.len(),
+                vertices.len()
+            );
+        }
+
+        let facet_count = polytope.facets.len() as i32;
+        info.facet_count_list[i].get_or_insert(facet_count);
+        if info.point_count_list[i].is_none() {
+            info.point_count_list[i] = Some(polytope.lattice_points().len() as i32);
+        }
+
+        if polytope.has_interior_origin() {
+            ip_count += 1;
+        }
+        if !polytope.is_reflexive() {
+            continue;
+        }
+        reflexive_count += 1;
+
+        if info.dual_point_count_list[i].is_none() {
+            let dual = polytope.dual()?;
+            info.dual_point_count_list[i] = Some(dual.lattice_points().len() as i32);
+        }
+
+        if info.hodge_number_lists.iter().any(|x| x[i].is_none()) {
+            if let Some(hodge_numbers) = polytope.hodge_numbers()? {
+                for (list, &h) in info.hodge_number_lists.iter_mut().zip(&hodge_numbers) {
+                    list[i].get_or_insert(h as i32);
+                }
+                if let Some(chi) = euler_characteristic(&hodge_numbers) {
+                    info.euler_characteristic_list[i].get_or_insert(chi as i32);
+                }
+            }
+        }
+    }
+
+    println!("IP polytopes: {}", ip_count);
+    println!("reflexive polytopes: {}", reflexive_count);
+
+    Ok(())
+}
+
+/// Computes the facet normals of each reflexive polytope, which are the vertices of the dual
+/// polytope, and checks or fills in the facet counts. Polytopes that are not reflexive get no
+/// dual vertices.
+fn compute_dual(info: &mut PolytopeInfo) -> Result<()> {
+    let mut dual_coordinates = Vec::new();
+    let mut dual_vertex_counts = Vec::new();
+    let mut facet_counts = Vec::new();
+
+    for (i, vertices) in info.vertex_lists().enumerate() {
+        let polytope = Polytope::new(&vertices).with_context(|| format!("polytope {}", i + 1))?;
+
+        facet_counts.push(polytope.facets.len() as i32);
+
+        if !polytope.is_reflexive() {
+            dual_vertex_counts.push(None);
+            continue;
+        }
+
+        dual_vertex_counts.push(Some(polytope.facets.len() as i32));
+
+        let mut normals: Vec<_> = polytope.facets.into_iter().map(|f| f.normal).collect();
+        normals.sort();
+        dual_coordinates.extend(normals.iter().flatten().map(|&x| x as i32));
+    }
+
+    let non_reflexive_count = dual_vertex_counts.iter().filter(|x| x.is_none()).count();
+    if non_reflexive_count > 0 {
+        println!(
+            "polytopes without dual vertices, not reflexive: {}",
+            non_reflexive_count
+        );
+    }
+
+    for (i, (facet_count, computed)) in info
+        .facet_count_list
+        .iter_mut()
+        .zip(facet_counts)
+        .enumerate()
+    {
+        match facet_count {
+            Some(expected) if *expected != computed => bail!(
+                "polytope {}: facet count expected {}, computed {}",
+                i + 1,
+                expected,
+                computed
+            ),
+            _ => *facet_count = Some(computed),
+        }
+    }
+
+    info.dual_coordinate_list = Some(dual_coordinates);
+    info.dual_vertex_count_list = dual_vertex_counts;
+
+    Ok(())
+}
+
+/// A 64-bit FNV-1a hash of a normal form, which is stable across platforms and releases.
+fn normal_form_hash(dimension: usize, vertices: &[Vec<i64>]) -> i64 {
+    const OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+    const PRIME: u64 = 0x100000001b3;
+
+    let values = [dimension as i32, vertices.len() as i32]
+        .into_iter()
+        .chain(vertices.iter().flatten().map(|&x| x as i32));
+
+    let mut hash = OFFSET_BASIS;
+    for value in values {
+        for byte in value.to_le_bytes() {
+            hash ^= u64::from(byte);
+            hash = hash.wrapping_mul(PRIME);
+        }
+    }
+
+    hash as i64
+}
+
+/// Computes the normal form of each polytope, keeping its vertices only if `with_coordinates` is
+/// set.
+fn compute_normal_forms(info: &mut PolytopeInfo, with_coordinates: bool) -> Result<()> {
+    let mut coordinates = Vec::new();
+    let mut vertex_counts = Vec::new();
+    let mut hashes = Vec::new();
+
+    for (i, vertices) in info.vertex_lists().enumerate() {
+        let polytope = Polytope::new(&vertices).with_context(|| format!("polytope {}", i + 1))?;
+        let normal_form = polytope.normal_form();
+
+        hashes.push(normal_form_hash(info.dimension, &normal_form));
+        if with_coordinates {
+            coordinates.extend(normal_form.iter().flatten().map(|&x| x as i32));
+            vertex_counts.push(normal_form.len() as i32);
+        }
+    }
+
+    info.normal_form_hash_list = Some(hashes);
+    if with_coordinates {
+        info.normal_form_coordinate_list = Some(coordinates);
+        info.normal_form_vertex_count_list = vertex_counts;
+    }
+
+    Ok(())
+}
+
+/// Reads the normal forms of the polytopes in a Parquet file written by `palp`, or by `ipws` for
+/// reflexive weight systems, from its `normal_form` column if it has one and from its `vertices`
+/// column otherwise.
+fn read_parquet_normal_forms(path: &Path) -> Result<Vec<Vec<Vec<i64>>>> {
+    let mut table = ParquetTable::open(path)?;
+
+    let has_column = |name: &str| table.names().iter().any(|x| x == name);
+    let has_normal_form = has_column("normal_form");
+    if !has_normal_form && !has_column("vertices") {
+        bail!(
+            "{}: neither normal_form nor vertices column",
+            path.display()
+        );
+    }
+
+    let name = if has_normal_form {
+        "normal_form"
+    } else {
+        "vertices"
+    };
+    table.select(&[name.to_owned()])?;
+
+    let mut normal_forms = Vec::new();
+    for g in 0..table.num_row_groups() {
+        let columns = table.read_row_group(g)?;
+
+        for i in 0..columns[0].len() {
+            let index = normal_forms.len() + 1;
+            let lists = columns[0].lists(i).with_context(|| {
+                format!("{}: polytope {}: {} missing", path.display(), index, name)
+            })?;
+            let vertices: Vec<Vec<i64>> = lists
+                .iter()
+                .map(|v| v.iter().map(|&x| x.into()).collect())
+                .collect();
+
+            if has_normal_form {
+                normal_forms.push(vertices);
+            } else {
+                let polytope = Polytope::new(&vertices)
+                    .with_context(|| format!("{}: polytope {}", path.display(), index))?;
+                normal_forms.push(polytope.normal_form());
+            }
+        }
+    }
+
+    Ok(normal_forms)
+}
+
+fn dedupe(args: PalpDedupeArgs) -> Result<()> {
+    // the inputs are named by their path, and PALP files with several dimensions are split into
+    // one input per dimension
+    let mut labels = Vec::new();
+    let mut normal_form_lists = Vec::new();
+    for palp_in in args.palp_in {
+        let infos = read_polytope_info(Some(palp_in.clone()), None, |_| true)?;
+        let several_dimensions = infos.len() > 1;
+
+        for info in infos.values() {
+            let label = if several_dimensions {
+                format!("{} ({}d)", palp_in.display(), info.dimension)
+            } else {
+                palp_in.display().to_string()
+            };
+            let normal_forms: Vec<Vec<Vec<i64>>> = info
+                .vertex_lists()
+                .enumerate()
+                .map(|(i, vertices)| {
+                    let polytope = Polytope::new(&vertices)
+                        .with_context(|| format!("{}: polytope {}", label, i + 1))?;
+                    Ok(polytope.normal_form())
+                })
+                .collect::<Result<_>>()?;
+            labels.push(label);
+            normal_form_lists.push(normal_forms);
+        }
+    }
+    for parquet_in in args.parquet_in {
+        normal_form_lists.push(read_parquet_normal_forms(&parquet_in)?);
+        labels.push(parquet_in.display().to_string());
+    }
+
+    // polytopes by dimension and normal form, in the order in which they were first seen
+    let mut first_seen = BTreeMap::<usize, HashMap<_, (usize, usize)>>::new();
+    let mut count = 0;
+    let mut duplicates = 0;
+
+    for (input, normal_forms) in normal_form_lists.into_iter().enumerate() {
+        for (i, normal_form) in normal_forms.into_iter().enumerate() {
+            count += 1;
+            let dimension = normal_form.first().map_or(0, Vec::len);
+            match first_seen.entry(dimension).or_default().entry(normal_form) {
+                Entry::Occupied(entry) => {
+                    let (first_input, first_index) = *entry.get();
+                    println!(
+                        "{}: polytope {} duplicates {}: polytope {}",
+                        labels[input],
+                        i + 1,
+                        labels[first_input],
+                        first_index + 1
+                    );
+                    duplicates += 1;
+                }
+                Entry::Vacant(entry) => {
+                    entry.insert((input, i));
+                }
+            }
+        }
+    }
+
+    println!("polytopes: {}", count);
+    println!("distinct: {}", count - duplicates);
+    println!("duplicates: {}", duplicates);
+
+    Ok(())
+}
+
+const DIMENSION_PLACEHOLDER: &str = "{dim}";
+
+pub fn run(args: PalpArgs) -> Result<()> {
+    if let Some(command) = args.command {
+        return match command {
+            PalpCommands::VerifyCounts(args) => verify_counts(args),
+            PalpCommands::CheckHodge(args) => check_hodge(args),
+            PalpCommands::VerifyHodge(args) => verify_hodge(args),
+            PalpCommands::Dedupe(args) => dedupe(args),
+        };
+    }
+
+    let has_output = args.parquet_out.is_some() || args.ipc_out.is_some();
+    // the polytopes read for the other outputs are reused for polymake and Sage
+    let mut polytope_info = None;
+
+    if let (Some(palp_in), true) = (&args.palp_in, has_output) {
+        let input = std::fs::read_to_string(palp_in)?;
+        let mut rejects = String::new();
+        // a placeholder for the dimension in the output paths routes each dimension to its own
+        // file
+        let output_path = |path: &Option<PathBuf>, dimension: usize| {
+            path.as_ref().map(|path| {
+                let path = path.to_string_lossy();
+                PathBuf::from(path.replace(DIMENSION_PLACEHOLDER, &dimension.to_string()))
+            })
+        };
+        let has_placeholder: Vec<bool> = [&args.parquet_out, &args.ipc_out]
+            .into_iter()
+            .flatten()
+            .map(|path| path.to_string_lossy().contains(DIMENSION_PLACEHOLDER))
+            .collect();
+        let split_dimensions = has_placeholder.contains(&true);
+        if split_dimensions && has_placeholder.contains(&false) {
+            bail!(
+                "either both or none of --parquet-out and --ipc-out must contain {}",
+                DIMENSION_PLACEHOLDER
+            );
+        }
+
+        let mut polytope_infos = parse_palp(
+            &input,
+            split_dimensions,
+            args.preserve_layout,
+            args.skip_invalid.then_some(&mut rejects),
+        )?;
+        for polytope_info in polytope_infos.values_mut() {
+            if args.fill_header {
+                fill_header(polytope_info)?;
+            }
+            if args.with_dual {
+                compute_dual(polytope_info)?;
+            }
+            if args.with_normal_form || args.with_normal_form_hash {
+                compute_normal_forms(polytope_info, args.with_normal_form)?;
+            }
+        }
+        if let Some(reject_out) = args.reject_out {
+            std::fs::write(reject_out, rejects)?;
+        }
+
+        for (&dimension, polytope_info) in &polytope_infos {
+            println!(
+                "dimension {}: {} polytopes",
+                dimension,
+                polytope_info.vertex_count_list.len()
+            );
+            let output = ParquetOutput::new(
+                output_path(&args.parquet_out, dimension),
+                output_path(&args.ipc_out, dimension),
+            )?
+            .expect("output");
+            write_parquet(output, polytope_info, args.columns.as_deref())?;
+        }
+
+        if args.polymake_out.is_some() || args.sage_out.is_some() {
+            if polytope_infos.len() > 1 {
+                bail!("--polymake-out and --sage-out need polytopes of a single dimension");
+            }
+            polytope_info = polytope_infos.pop_first().map(|(_, info)| info);
+        }
+    } else if let (Some(palp_out), Some(parquet_in)) = (&args.palp_out, &args.parquet_in) {
+        let mut info = PolytopeInfo::default();
+        read_parquet(parquet_in, &mut info, |_| true)?;
+        let output = format_palp(&info, args.palp_layout)?;
+        std::fs::write(palp_out, output)?;
+        polytope_info = Some(info);
+    } else if args.polymake_out.is_none() && args.sage_out.is_none() {
+        println!("Nothing to do.");
+    }
+
+    if args.polymake_out.is_some() || args.sage_out.is_some() {
+        let polytope_info = match polytope_info {
+            Some(info) => info,
+            None => {
+                let mut polytope_infos =
+                    read_polytope_info(args.palp_in, args.parquet_in, |_| true)?;
+                if polytope_infos.len() > 1 {
+                    bail!("--polymake-out and --sage-out need polytopes of a single dimension");
+                }
+                polytope_infos.pop_first().expect("polytopes").1
+            }
+        };
+        let limit = min(
+            args.limit.unwrap_or(usize::MAX),
+            polytope_info.vertex_count_list.len(),
+        );
+
+        if let Some(polymake_out) = args.polymake_out {
+            write_polymake(&polytope_info, polymake_out, limit)?;
+        }
+        if let Some(sage_out) = args.sage_out {
+            write_sage(&polytope_info, sage_out, limit)?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const QUINTIC: &str = "\
+4 5  M:126 5 N:6 5 H:1,101 [-200]
+   -1    4   -1   -1   -1
+   -1   -1    4   -1   -1
+   -1   -1   -1    4   -1
+   -1   -1   -1   -1    4
+";
+
+    fn parse_single(input: &str) -> PolytopeInfo {
+        let mut infos = parse_palp(input, false, false, None).unwrap();
+        assert_eq!(infos.len(), 1);
+        infos.pop_first().unwrap().1
+    }
+
+    fn write_temporary(info: &PolytopeInfo, columns: Option<&[String]>, name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("{}-{}.parquet", name, std::process::id()));
+        let output = ParquetOutput::parquet(fs::File::create(&path).unwrap());
+        write_parquet(output, info, columns).unwrap();
+        path
+    }
+
+    #[test]
+    fn header_formats() {
+        let header = parse_header("4 5  M:126 5 N:6 5 H:1,101 [-200]").unwrap();
+        assert_eq!((header.rows, header.columns), (4, 5));
+        assert_eq!(header.point_count, Some(126));
+        assert_eq!(header.vertex_count, Some(5));
+        assert_eq!(header.dual_point_count, Some(6));
+        assert_eq!(header.facet_count, Some(5));
+        assert_eq!(header.hodge_numbers, Some(vec![1, 101]));
+        assert_eq!(header.euler_characteristic, Some(-200));
+
+        let header = parse_header("4 7  M:12 7 F:9").unwrap();
+        assert_eq!(header.point_count, Some(12));
+        assert_eq!(header.facet_count, Some(9));
+        assert_eq!(header.dual_point_count, None);
+
+        let header = parse_header("4 5  M:126 5").unwrap();
+        assert_eq!(header.point_count, Some(126));
+        assert_eq!(header.facet_count, None);
+
+        let header = parse_header(" 5 4 ").unwrap();
+        assert_eq!((header.rows, header.columns), (5, 4));
+        assert_eq!(header.point_count, None);
+    }
+
+    #[test]
+    fn header_trailing_text() {
+        let header = parse_header("4 5  M:126 5 N:6 5 H:1,101 [-200] #1").unwrap();
+        assert_eq!(header.euler_characteristic, Some(-200));
+        assert!(parse_header("4 5 x").is_err());
+        assert!(parse_header("x 4 5").is_err());
+    }
+
+    #[test]
+    fn header_without_facet_count() {
+        let info = parse_single(&QUINTIC.replace(" N:6 5 H:1,101 [-200]", ""));
+        assert_eq!(info.facet_count_list, [None]);
+        assert_eq!(format_header(&info, 0, false), "4 5  M:126 5");
+        assert_eq!(header_values(&info, 0, false), [4, 5, 126, 5]);
+    }
+
+    #[test]
+    fn invalid_blocks_are_skipped() {
+        let invalid = "\
+4 5  M:126 5 N:6 5 H:1,101 [-200]
+   -1    4   -1   -1   -1
+   -1   -1    x   -1   -1
+";
+        // the truncated block ends at the next header, which is read again
+        let truncated = "\
+4 5
+   -1    4   -1   -1   -1
+";
+        let input = [QUINTIC, invalid, QUINTIC, truncated, QUINTIC].concat();
+
+        let error = parse_palp(&input, false, false, None).err().unwrap();
+        assert_eq!(
+            error.to_string(),
+            "line 8 (polytope 2): invalid coordinate: -1   -1    x   -1   -1"
+        );
+
+        let mut rejects = String::new();
+        let infos = parse_palp(&input, false, false, Some(&mut rejects)).unwrap();
+        assert_eq!(infos[&4].vertex_count_list, [5, 5, 5]);
+        assert_eq!(rejects, [invalid, truncated].concat());
+    }
+
+    #[test]
+    fn mixed_dimensions() {
+        let octahedron = "\
+3 6
+    1    0    0   -1    0    0
+    0    1    0    0   -1    0
+    0    0    1    0    0   -1
+";
+        let input = [QUINTIC, octahedron, QUINTIC].concat();
+
+        let error = parse_palp(&input, false, false, None).err().unwrap();
+        assert_eq!(
+            error.to_string(),
+            "line 6 (polytope 2): varying dimension: 3 6"
+        );
+
+        let infos = parse_palp(&input, true, false, None).unwrap();
+        assert_eq!(infos.keys().collect::<Vec<_>>(), [&3, &4]);
+        assert_eq!(infos[&3].vertex_count_list, [6]);
+        assert_eq!(infos[&3].coordinate_list[..6], [1, 0, 0, 0, 1, 0]);
+        assert_eq!(infos[&4].vertex_count_list, [5, 5]);
+
+        assert!(parse_palp(&input, true, true, None).is_err());
+    }
+
+    #[test]
+    fn preserved_layout_round_trip() {
+        // `|` marks the end of lines with trailing spaces
+        let input = "\
+# generated by poly.x
+5 4  M:126 5 N:6 5 H:1,101 [-200]
+ -1 -1 -1 -1
+  4 -1 -1 -1
+ -1  4 -1 -1
+ -1 -1  4 -1
+ -1 -1 -1  4
+
+4 5 M:126 5 N:6 5 H:1,101 [-200]
+  -1   4  -1  -1  -1 |
+  -1  -1   4  -1  -1 |
+  -1  -1  -1   4  -1 |
+  -1  -1  -1  -1   4 |
+4 5
+   -1    4   -1   -1   -1
+   -1   -1    4   -1   -1
+   -1   -1   -1    4   -1
+   -1   -1   -1   -1    4"
+            .replace('|', "");
+
+        let info = parse_palp(&input, false, true, None)
+            .unwrap()
+            .remove(&4)
+            .unwrap();
+        assert_eq!(format_palp(&info, None).unwrap(), input);
+
+        // the layout is stored in the metadata of the Parquet file
+        let path = write_temporary(&info, None, "layout");
+        let mut read = PolytopeInfo::default();
+        read_parquet(&path, &mut read, |_| true).unwrap();
+        assert_eq!(format_palp(&read, None).unwrap(), input);
+        fs::remove_file(path).unwrap();
+
+        let error = parse_palp(&input.replace('\n', "\r\n"), false, true, None)
+            .err()
+            .unwrap();
+        assert_eq!(
+            error.to_string(),
+            "the layout of CRLF line endings cannot be preserved"
+        );
+    }
+
+    #[test]
+    fn matrix_layouts() {
+        let info = parse_palp(QUINTIC, false, true, None)
+            .unwrap()
+            .remove(&4)
+            .unwrap();
+        assert_eq!(format_palp(&info, None).unwrap(), QUINTIC);
+        assert_eq!(
+            format_palp(&info, Some(PalpMatrixLayout::Columns)).unwrap(),
+            QUINTIC
+        );
+
+        let rows = format_palp(&info, Some(PalpMatrixLayout::Rows)).unwrap();
+        assert_eq!(
+            rows,
+            "\
+5 4  M:126 5 N:6 5 H:1,101 [-200]
+   -1   -1   -1   -1
+    4   -1   -1   -1
+   -1    4   -1   -1
+   -1   -1    4   -1
+   -1   -1   -1    4
+"
+        );
+
+        let transposed = parse_single(&rows);
+        assert_eq!(transposed.coordinate_list, info.coordinate_list);
+    }
+
+    #[test]
+    fn filled_headers() {
+        let mut info = parse_single(&QUINTIC.replace("  M:126 5 N:6 5 H:1,101 [-200]", ""));
+        fill_header(&mut info).unwrap();
+        assert_eq!(
+            format_header(&info, 0, false),
+            QUINTIC.lines().next().unwrap()
+        );
+
+        // reflexive, IP but not reflexive, and without the origin in the interior
+        let input = "\
+3 6
+    1    0    0   -1    0    0
+    0    1    0    0   -1    0
+    0    0    1    0    0   -1
+3 6
+    2    0    0   -2    0    0
+    0    2    0    0   -2    0
+    0    0    2    0    0   -2
+3 4
+    1    0    0    1
+    0    1    0    1
+    0    0    1    1
+";
+        let mut info = parse_single(input);
+        fill_header(&mut info).unwrap();
+        let headers: Vec<_> = (0..3).map(|i| format_header(&info, i, false)).collect();
+        assert_eq!(
+            headers,
+            ["3 6  M:7 6 N:27 8", "3 6  M:25 6 F:8", "3 4  M:4 4 F:4"]
+        );
+
+        let mut info = parse_single("3 5\n1 0 0 -1 0\n0 1 0 0 0\n0 0 1 0 0\n");
+        let error = fill_header(&mut info).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "polytope 1: only 4 of 5 points are vertices"
+        );
+    }
+
+    #[test]
+    fn projected_parquet_round_trip() {
+        let info = parse_single(QUINTIC);
+        let columns = ["vertex_count".to_owned(), "h11".to_owned()];
+        let path = write_temporary(&info, Some(&columns), "projected");
+
+        let mut read = PolytopeInfo::default();
+        read_parquet(&path, &mut read, |name| name.starts_with("h1")).unwrap();
+        assert_eq!(read.vertex_count_list, [5]);
+        assert_eq!(read.hodge_number_lists[0], [Some(1)]);
+        assert_eq!(read.hodge_number_lists[1], [None]);
+        assert_eq!(read.point_count_list, [None]);
+
+        // the vertices are needed to write PALP output
+        let mut read = PolytopeInfo::default();
+        let error = read_parquet(&path, &mut read, |_| true).unwrap_err();
+        assert_eq!(error.to_string(), "column vertices missing");
+
+        fs::remove_file(path).unwrap();
+    }
 }
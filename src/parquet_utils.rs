@@ -1,10 +1,22 @@
-use anyhow::Result;
+use anyhow::{bail, Context, Result};
+use arrow_array::ArrayRef;
 use parquet::basic::{LogicalType, Repetition, Type as PhysicalType};
-use parquet::file::writer::SerializedRowGroupWriter;
-use parquet::schema::types::Type as SchemaType;
+use parquet::file::metadata::KeyValue;
+use parquet::file::properties::WriterProperties;
+use parquet::file::reader::{ChunkReader, FileReader as _};
+use parquet::file::serialized_reader::SerializedFileReader;
+use parquet::file::writer::{SerializedFileWriter, SerializedRowGroupWriter};
+use parquet::schema::types::{ColumnDescriptor, SchemaDescriptor, Type as SchemaType};
+use std::fs::File;
 use std::io::Write;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
+use crate::ipc_utils::{
+    build_int_array, build_int_list_of_lists_array, build_long_array, build_optional_int_array,
+    IpcWriter,
+};
+
 pub fn build_parquet_int_field(name: &str) -> Result<Arc<SchemaType>> {
     Ok(Arc::new(
         SchemaType::primitive_type_builder(name, PhysicalType::INT32)
@@ -13,8 +25,32 @@ pub fn build_parquet_int_field(name: &str) -> Result<Arc<SchemaType>> {
     ))
 }
 
-// https://github.com/apache/parquet-format/blob/master/LogicalTypes.md#lists
+pub fn build_parquet_long_field(name: &str) -> Result<Arc<SchemaType>> {
+    Ok(Arc::new(
+        SchemaType::primitive_type_builder(name, PhysicalType::INT64)
+            .with_repetition(Repetition::REQUIRED)
+            .build()?,
+    ))
+}
+
+pub fn build_parquet_optional_int_field(name: &str) -> Result<Arc<SchemaType>> {
+    Ok(Arc::new(
+        SchemaType::primitive_type_builder(name, PhysicalType::INT32)
+            .with_repetition(Repetition::OPTIONAL)
+            .build()?,
+    ))
+}
+
 pub fn build_parquet_int_list_of_lists_field(name: &str) -> Result<Arc<SchemaType>> {
+    build_list_of_lists_field(name, Repetition::REQUIRED)
+}
+
+pub fn build_parquet_optional_int_list_of_lists_field(name: &str) -> Result<Arc<SchemaType>> {
+    build_list_of_lists_field(name, Repetition::OPTIONAL)
+}
+
+// https://github.com/apache/parquet-format/blob/master/LogicalTypes.md#lists
+fn build_list_of_lists_field(name: &str, repetition: Repetition) -> Result<Arc<SchemaType>> {
     let inner_element = SchemaType::primitive_type_builder("element", PhysicalType::INT32)
         .with_repetition(Repetition::REQUIRED)
         .build()?;
@@ -37,7 +73,7 @@ pub fn build_parquet_int_list_of_lists_field(name: &str) -> Result<Arc<SchemaTyp
 
     let field = SchemaType::group_type_builder(name)
         .with_logical_type(Some(LogicalType::List))
-        .with_repetition(Repetition::REQUIRED)
+        .with_repetition(repetition)
         .with_fields([Arc::new(outer_list)].to_vec())
         .build()?;
 
@@ -45,17 +81,61 @@ pub fn build_parquet_int_list_of_lists_field(name: &str) -> Result<Arc<SchemaTyp
 }
 
 pub fn write_parquet_int_column<W: Write + Send>(
-    row_group_writer: &mut SerializedRowGroupWriter<W>,
+    row_group_writer: &mut RowGroupWriter<W>,
     data: &[i32],
 ) -> Result<()> {
     use parquet::data_type::Int32Type;
 
-    let mut col_writer = row_group_writer.next_column()?.expect("column");
+    if let Some(row_group_writer) = &mut row_group_writer.parquet {
+        let mut col_writer = row_group_writer.next_column()?.expect("column");
 
-    col_writer
-        .typed::<Int32Type>()
-        .write_batch(data, None, None)?;
-    col_writer.close()?;
+        col_writer
+            .typed::<Int32Type>()
+            .write_batch(data, None, None)?;
+        col_writer.close()?;
+    }
+    row_group_writer.push_array(|| build_int_array(data));
+
+    Ok(())
+}
+
+pub fn write_parquet_long_column<W: Write + Send>(
+    row_group_writer: &mut RowGroupWriter<W>,
+    data: &[i64],
+) -> Result<()> {
+    use parquet::data_type::Int64Type;
+
+    if let Some(row_group_writer) = &mut row_group_writer.parquet {
+        let mut col_writer = row_group_writer.next_column()?.expect("column");
+
+        col_writer
+            .typed::<Int64Type>()
+            .write_batch(data, None, None)?;
+        col_writer.close()?;
+    }
+    row_group_writer.push_array(|| build_long_array(data));
+
+    Ok(())
+}
+
+pub fn write_optional_parquet_int_column<W: Write + Send>(
+    row_group_writer: &mut RowGroupWriter<W>,
+    data: &[Option<i32>],
+) -> Result<()> {
+    use parquet::data_type::Int32Type;
+
+    if let Some(row_group_writer) = &mut row_group_writer.parquet {
+        let values: Vec<i32> = data.iter().flatten().copied().collect();
+        let definition_levels: Vec<i16> = data.iter().map(|x| x.is_some() as i16).collect();
+
+        let mut col_writer = row_group_writer.next_column()?.expect("column");
+
+        col_writer
+            .typed::<Int32Type>()
+            .write_batch(&values, Some(&definition_levels), None)?;
+        col_writer.close()?;
+    }
+    row_group_writer.push_array(|| build_optional_int_array(data));
 
     Ok(())
 }
@@ -79,3 +159,809 @@ pub fn write_repeated_parquet_int_column<W: Write + Send>(
 
     Ok(count)
 }
+
+/// Writes a column built with `build_parquet_int_list_of_lists_field`. `data` holds the inner
+/// lists of length `element_length` back to back, and `list_lengths` the number of inner lists
+/// in each row.
+pub fn write_parquet_int_list_of_lists_column<W: Write + Send>(
+    row_group_writer: &mut RowGroupWriter<W>,
+    data: &[i32],
+    list_lengths: &[i32],
+    element_length: usize,
+) -> Result<()> {
+    let list_lengths = list_lengths.iter().map(|&x| Some(x));
+    write_list_of_lists_column(row_group_writer, data, list_lengths, element_length, 0)
+}
+
+/// Writes a column built with `build_parquet_optional_int_list_of_lists_field`, like
+/// `write_parquet_int_list_of_lists_column` with `None` list lengths for nulls.
+pub fn write_optional_parquet_int_list_of_lists_column<W: Write + Send>(
+    row_group_writer: &mut RowGroupWriter<W>,
+    data: &[i32],
+    list_lengths: &[Option<i32>],
+    element_length: usize,
+) -> Result<()> {
+    let list_lengths = list_lengths.iter().copied();
+    write_list_of_lists_column(row_group_writer, data, list_lengths, element_length, 1)
+}
+
+/// Writes the levels of a list of lists column, where a row is null at definition level 0 if
+/// `null_levels` is 1, empty at `null_levels`, has an empty inner list at `null_levels + 1`, and a
+/// value at `null_levels + 2`.
+fn write_list_of_lists_column<W: Write + Send>(
+    row_group_writer: &mut RowGroupWriter<W>,
+    data: &[i32],
+    list_lengths: impl Iterator<Item = Option<i32>> + Clone,
+    element_length: usize,
+    null_levels: i16,
+) -> Result<()> {
+    row_group_writer
+        .push_array(|| build_int_list_of_lists_array(data, list_lengths.clone(), element_length));
+
+    let Some(row_group_writer) = &mut row_group_writer.parquet else {
+        return Ok(());
+    };
+
+    let mut definition_levels = Vec::with_capacity(data.len());
+    let mut repetition_levels = Vec::with_capacity(data.len());
+
+    for length in list_lengths {
+        let Some(length) = length else {
+            definition_levels.push(0);
+            repetition_levels.push(0);
+            continue;
+        };
+
+        if length == 0 {
+            definition_levels.push(null_levels);
+            repetition_levels.push(0);
+            continue;
+        }
+
+        for v in 0..length {
+            let list_repetition_level = if v == 0 { 0 } else { 1 };
+
+            // an empty inner list has no values, but still needs levels
+            if element_length == 0 {
+                repetition_levels.push(list_repetition_level);
+                definition_levels.push(null_levels + 1);
+                continue;
+            }
+
+            for i in 0..element_length {
+                let value = if i == 0 { list_repetition_level } else { 2 };
+                repetition_levels.push(value);
+                definition_levels.push(null_levels + 2);
+            }
+        }
+    }
+
+    let count = write_repeated_parquet_int_column(
+        row_group_writer,
+        data,
+        &definition_levels,
+        &repetition_levels,
+    )?;
+    assert_eq!(count, data.len());
+
+    Ok(())
+}
+
+/// Values of one column in a row group, with one variant for each of the field builders above
+pub enum ColumnValues {
+    Int(Vec<i32>),
+    Long(Vec<i64>),
+    OptionalInt(Vec<Option<i32>>),
+    IntListOfLists(Vec<Vec<Vec<i32>>>),
+    OptionalIntListOfLists(Vec<Option<Vec<Vec<i32>>>>),
+}
+
+impl ColumnValues {
+    pub fn len(&self) -> usize {
+        match self {
+            ColumnValues::Int(values) => values.len(),
+            ColumnValues::Long(values) => values.len(),
+            ColumnValues::OptionalInt(values) => values.len(),
+            ColumnValues::IntListOfLists(values) => values.len(),
+            ColumnValues::OptionalIntListOfLists(values) => values.len(),
+        }
+    }
+
+    /// The list of lists of row `i`, or `None` if it is null. Fails for integer columns.
+    pub fn lists(&self, i: usize) -> Option<&[Vec<i32>]> {
+        match self {
+            ColumnValues::IntListOfLists(values) => Some(&values[i]),
+            ColumnValues::OptionalIntListOfLists(values) => values[i].as_deref(),
+            _ => panic!("not a list of lists column"),
+        }
+    }
+
+    /// The values of the rows `i` with `keep[i]`.
+    pub fn filter(&self, keep: &[bool]) -> ColumnValues {
+        fn filter<T: Clone>(values: &[T], keep: &[bool]) -> Vec<T> {
+            values
+                .iter()
+                .zip(keep)
+                .filter(|(_, &keep)| keep)
+                .map(|(x, _)| x.clone())
+                .collect()
+        }
+
+        match self {
+            ColumnValues::Int(values) => ColumnValues::Int(filter(values, keep)),
+            ColumnValues::Long(values) => ColumnValues::Long(filter(values, keep)),
+            ColumnValues::OptionalInt(values) => ColumnValues::OptionalInt(filter(values, keep)),
+            ColumnValues::IntListOfLists(values) => {
+                ColumnValues::IntListOfLists(filter(values, keep))
+            }
+            ColumnValues::OptionalIntListOfLists(values) => {
+                ColumnValues::OptionalIntListOfLists(filter(values, keep))
+            }
+        }
+    }
+}
+
+/// Types of the columns written by the field builders above
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ColumnKind {
+    Int,
+    Long,
+    OptionalInt,
+    IntListOfLists,
+    OptionalIntListOfLists,
+}
+
+impl ColumnKind {
+    pub fn is_list_of_lists(self) -> bool {
+        matches!(
+            self,
+            ColumnKind::IntListOfLists | ColumnKind::OptionalIntListOfLists
+        )
+    }
+}
+
+/// The kind of a column of a Parquet file written by this program
+fn column_kind(column: &ColumnDescriptor) -> Result<ColumnKind> {
+    let levels = (column.max_def_level(), column.max_rep_level());
+
+    Ok(match (column.physical_type(), levels) {
+        (PhysicalType::INT32, (0, 0)) => ColumnKind::Int,
+        (PhysicalType::INT32, (1, 0)) => ColumnKind::OptionalInt,
+        (PhysicalType::INT32, (2, 2)) => ColumnKind::IntListOfLists,
+        (PhysicalType::INT32, (3, 2)) => ColumnKind::OptionalIntListOfLists,
+        (PhysicalType::INT64, (0, 0)) => ColumnKind::Long,
+        _ => bail!(
+            "column {}: unsupported Parquet column type",
+            column.path().parts()[0]
+        ),
+    })
+}
+
+/// Reads Parquet files written by this program one row group at a time, without knowing the
+/// schema in advance. Only the selected columns are read, which are all columns by default.
+pub struct ParquetTable<R: ChunkReader + 'static = File> {
+    reader: SerializedFileReader<R>,
+    names: Vec<String>,
+    kinds: Vec<ColumnKind>,
+    indices: Vec<usize>,
+}
+
+impl ParquetTable {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let file = File::open(&path)
+            .with_context(|| format!("open {}", path.as_ref().to_string_lossy()))?;
+        ParquetTable::new(file)
+    }
+}
+
+impl<R: ChunkReader + 'static> ParquetTable<R> {
+    pub fn new(data: R) -> Result<Self> {
+        let reader = SerializedFileReader::new(data)?;
+
+        let schema = reader.metadata().file_metadata().schema_descr();
+        let mut names = Vec::with_capacity(schema.num_columns());
+        let mut kinds = Vec::with_capacity(schema.num_columns());
+
+        for c in 0..schema.num_columns() {
+            let column = schema.column(c);
+            names.push(column.path().parts()[0].clone());
+            kinds.push(column_kind(&column)?);
+        }
+
+        let indices = (0..names.len()).collect();
+
+        Ok(Self {
+            reader,
+            names,
+            kinds,
+            indices,
+        })
+    }
+
+    /// Restricts the table to the given columns, in the given order.
+    pub fn select(&mut self, names: &[String]) -> Result<()> {
+        let mut positions = Vec::with_capacity(names.len());
+
+        for name in names {
+            let c = self
+                .names
+                .iter()
+                .position(|x| x == name)
+                .with_context(|| format!("unknown column {}", name))?;
+            if positions.contains(&c) {
+                bail!("column {} selected more than once", name);
+            }
+            positions.push(c);
+        }
+
+        self.names = names.to_vec();
+        self.kinds = positions.iter().map(|&c| self.kinds[c]).collect();
+        self.indices = positions.iter().map(|&c| self.indices[c]).collect();
+
+        Ok(())
+    }
+
+    pub fn names(&self) -> &[String] {
+        &self.names
+    }
+
+    pub fn kinds(&self) -> &[ColumnKind] {
+        &self.kinds
+    }
+
+    pub fn key_value_metadata(&self) -> Vec<(String, String)> {
+        let file_metadata = self.reader.metadata().file_metadata();
+
+        file_metadata
+            .key_value_metadata()
+            .map(|kv_metadata| {
+                kv_metadata
+                    .iter()
+                    .map(|kv| (kv.key.clone(), kv.value.clone().unwrap_or_default()))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    pub fn num_row_groups(&self) -> usize {
+        self.reader.metadata().num_row_groups()
+    }
+
+    /// The smallest and largest value of an integer column in a row group according to the
+    /// statistics, ignoring nulls, if they are known.
+    pub fn column_range(&self, g: usize, c: usize) -> Option<(i64, i64)> {
+        use parquet::file::statistics::Statistics;
+
+        let column = self.reader.metadata().row_group(g).column(self.indices[c]);
+
+        match column.statistics()? {
+            Statistics::Int32(s) if s.has_min_max_set() => {
+                Some((i64::from(*s.min()), i64::from(*s.max())))
+            }
+            Statistics::Int64(s) if s.has_min_max_set() => Some((*s.min(), *s.max())),
+            _ => None,
+        }
+    }
+
+    /// The number of rows of a row group.
+    pub fn row_group_len(&self, g: usize) -> usize {
+        self.reader.metadata().row_group(g).num_rows() as usize
+    }
+
+    pub fn read_row_group(&self, g: usize) -> Result<Vec<ColumnValues>> {
+        (0..self.names.len())
+            .map(|c| self.read_column(g, c))
+            .collect()
+    }
+
+    /// Reads a single column of a row group.
+    pub fn read_column(&self, g: usize, c: usize) -> Result<ColumnValues> {
+        use parquet::column::reader::ColumnReader;
+
+        let row_group_reader = self.reader.get_row_group(g)?;
+        let row_group_metadata = self.reader.metadata().row_group(g);
+
+        let (c, kind, name) = (self.indices[c], self.kinds[c], &self.names[c]);
+        let to_read = row_group_metadata.column(c).num_values() as usize;
+
+        let mut definition_levels = vec![0; to_read];
+        let mut repetition_levels = vec![0; to_read];
+
+        let column = match row_group_reader.get_column_reader(c)? {
+            ColumnReader::Int32ColumnReader(mut typed_reader) => {
+                let mut values = vec![0; to_read];
+                let (_, count, _) = typed_reader.read_records(
+                    to_read,
+                    Some(&mut definition_levels),
+                    Some(&mut repetition_levels),
+                    &mut values,
+                )?;
+                values.truncate(count);
+
+                match kind {
+                    ColumnKind::OptionalInt => {
+                        let mut values = values.into_iter();
+                        ColumnValues::OptionalInt(
+                            definition_levels
+                                .iter()
+                                .map(|&d| if d == 1 { values.next() } else { None })
+                                .collect(),
+                        )
+                    }
+                    ColumnKind::IntListOfLists => ColumnValues::IntListOfLists(
+                        assemble_list_of_lists(&values, &definition_levels, &repetition_levels, 0)
+                            .into_iter()
+                            .map(|x| x.expect("list of lists"))
+                            .collect(),
+                    ),
+                    ColumnKind::OptionalIntListOfLists => ColumnValues::OptionalIntListOfLists(
+                        assemble_list_of_lists(&values, &definition_levels, &repetition_levels, 1),
+                    ),
+                    _ => ColumnValues::Int(values),
+                }
+            }
+            ColumnReader::Int64ColumnReader(mut typed_reader) => {
+                let mut values = vec![0; to_read];
+                let (count, _, _) = typed_reader.read_records(to_read, None, None, &mut values)?;
+                values.truncate(count);
+                ColumnValues::Long(values)
+            }
+            _ => bail!("column {}: unsupported Parquet column type", name),
+        };
+
+        Ok(column)
+    }
+}
+
+/// Writes Parquet files with the columns read by `ParquetTable`, one row group at a time.
+pub struct ParquetTableWriter<W: Write + Send> {
+    writer: OutputWriter<W>,
+}
+
+impl<W: Write + Send> ParquetTableWriter<W> {
+    pub fn new(
+        output: W,
+        names: &[String],
+        kinds: &[ColumnKind],
+        metadata: &[(String, String)],
+    ) -> Result<Self> {
+        use parquet::basic::{Compression, ZstdLevel};
+        use parquet::file::properties::WriterVersion;
+
+        let writer_props = Arc::new(
+            WriterProperties::builder()
+                .set_writer_version(WriterVersion::PARQUET_2_0)
+                .set_compression(Compression::ZSTD(ZstdLevel::try_new(5)?))
+                .build(),
+        );
+
+        let fields = names
+            .iter()
+            .zip(kinds)
+            .map(|(name, kind)| match kind {
+                ColumnKind::Int => build_parquet_int_field(name),
+                ColumnKind::Long => build_parquet_long_field(name),
+                ColumnKind::OptionalInt => build_parquet_optional_int_field(name),
+                ColumnKind::IntListOfLists => build_parquet_int_list_of_lists_field(name),
+                ColumnKind::OptionalIntListOfLists => {
+                    build_parquet_optional_int_list_of_lists_field(name)
+                }
+            })
+            .collect::<Result<_>>()?;
+
+        let schema = SchemaType::group_type_builder("schema")
+            .with_fields(fields)
+            .build()?;
+
+        let mut writer = ParquetOutput::parquet(output).writer(Arc::new(schema), writer_props)?;
+
+        for (key, value) in metadata {
+            writer.append_key_value_metadata(KeyValue::new(key.clone(), value.clone()));
+        }
+
+        Ok(Self { writer })
+    }
+
+    pub fn write_row_group(&mut self, columns: &[ColumnValues]) -> Result<()> {
+        let mut row_group_writer = self.writer.next_row_group()?;
+
+        for column in columns {
+            match column {
+                ColumnValues::Int(values) => {
+                    write_parquet_int_column(&mut row_group_writer, values)?
+                }
+                ColumnValues::Long(values) => {
+                    write_parquet_long_column(&mut row_group_writer, values)?
+                }
+                ColumnValues::OptionalInt(values) => {
+                    write_optional_parquet_int_column(&mut row_group_writer, values)?
+                }
+                ColumnValues::IntListOfLists(values) => {
+                    let (data, list_lengths, element_length) =
+                        flatten_lists(values.iter().map(|x| Some(x.as_slice())))?;
+                    let list_lengths: Vec<i32> = list_lengths.into_iter().flatten().collect();
+
+                    write_parquet_int_list_of_lists_column(
+                        &mut row_group_writer,
+                        &data,
+                        &list_lengths,
+                        element_length,
+                    )?
+                }
+                ColumnValues::OptionalIntListOfLists(values) => {
+                    let (data, list_lengths, element_length) =
+                        flatten_lists(values.iter().map(Option::as_deref))?;
+
+                    write_optional_parquet_int_list_of_lists_column(
+                        &mut row_group_writer,
+                        &data,
+                        &list_lengths,
+                        element_length,
+                    )?
+                }
+            }
+        }
+
+        row_group_writer.close()?;
+
+        Ok(())
+    }
+
+    pub fn append_key_value_metadata(&mut self, key: &str, value: &str) {
+        self.writer
+            .append_key_value_metadata(KeyValue::new(key.to_owned(), value.to_owned()));
+    }
+
+    pub fn close(self) -> Result<()> {
+        self.writer.close()?;
+        Ok(())
+    }
+}
+
+/// Puts the inner lists of the rows back to back, returning them with the number of inner lists
+/// of each row and their common length.
+fn flatten_lists<'a>(
+    rows: impl Iterator<Item = Option<&'a [Vec<i32>]>> + Clone,
+) -> Result<(Vec<i32>, Vec<Option<i32>>, usize)> {
+    let element_length = rows
+        .clone()
+        .find_map(|lists| lists?.first().map(Vec::len))
+        .unwrap_or(0);
+
+    let mut data = Vec::new();
+    let mut list_lengths = Vec::new();
+
+    for lists in rows {
+        list_lengths.push(lists.map(|x| x.len() as i32));
+
+        for list in lists.into_iter().flatten() {
+            if list.len() != element_length {
+                bail!("lists of different lengths");
+            }
+            data.extend_from_slice(list);
+        }
+    }
+
+    Ok((data, list_lengths, element_length))
+}
+
+/// Destination of a table: a Parquet file, an Arrow IPC file with the same columns and metadata,
+/// or both.
+pub struct ParquetOutput<W: Write + Send = File> {
+    parquet: Option<W>,
+    ipc: Option<File>,
+}
+
+impl ParquetOutput {
+    pub fn new(parquet_path: Option<PathBuf>, ipc_path: Option<PathBuf>) -> Result<Option<Self>> {
+        if parquet_path.is_none() && ipc_path.is_none() {
+            return Ok(None);
+        }
+
+        Ok(Some(Self {
+            parquet: parquet_path.map(File::create).transpose()?,
+            ipc: ipc_path.map(File::create).transpose()?,
+        }))
+    }
+}
+
+impl<W: Write + Send> ParquetOutput<W> {
+    /// Only a Parquet file, written to `output`
+    pub fn parquet(output: W) -> Self {
+        Self {
+            parquet: Some(output),
+            ipc: None,
+        }
+    }
+
+    pub fn writer(
+        self,
+        schema: Arc<SchemaType>,
+        writer_props: Arc<WriterProperties>,
+    ) -> Result<OutputWriter<W>> {
+        let ipc = match self.ipc {
+            Some(file) => {
+                let descriptor = SchemaDescriptor::new(schema.clone());
+                let columns = descriptor.columns();
+                let names: Vec<String> = columns
+                    .iter()
+                    .map(|c| c.path().parts()[0].clone())
+                    .collect();
+                let kinds: Vec<ColumnKind> = columns
+                    .iter()
+                    .map(|c| column_kind(c))
+                    .collect::<Result<_>>()?;
+                Some(IpcWriter::new(file, &names, &kinds))
+            }
+            None => None,
+        };
+        let parquet = self
+            .parquet
+            .map(|output| SerializedFileWriter::new(output, schema, writer_props))
+            .transpose()?;
+
+        Ok(OutputWriter { parquet, ipc })
+    }
+}
+
+/// Writes a table to the files of a `ParquetOutput`, one row group at a time. The Arrow IPC file
+/// gets one record batch per row group, built from the same values as the Parquet columns.
+pub struct OutputWriter<W: Write + Send> {
+    parquet: Option<SerializedFileWriter<W>>,
+    ipc: Option<IpcWriter>,
+}
+
+impl<W: Write + Send> OutputWriter<W> {
+    /// Adds to the metadata of the files, which has to happen before the first row group for the
+    /// Arrow IPC file.
+    pub fn append_key_value_metadata(&mut self, kv: KeyValue) {
+        if let Some(ipc) = &mut self.ipc {
+            ipc.append_key_value_metadata(&kv.key, kv.value.as_deref().unwrap_or_default());
+        }
+        if let Some(parquet) = &mut self.parquet {
+            parquet.append_key_value_metadata(kv);
+        }
+    }
+
+    pub fn next_row_group(&mut self) -> Result<RowGroupWriter<'_, W>> {
+        Ok(RowGroupWriter {
+            parquet: self
+                .parquet
+                .as_mut()
+                .map(|writer| writer.next_row_group())
+                .transpose()?,
+            ipc: self.ipc.as_mut().map(|writer| (writer, Vec::new())),
+        })
+    }
+
+    pub fn close(self) -> Result<()> {
+        if let Some(parquet) = self.parquet {
+            parquet.close()?;
+        }
+        if let Some(ipc) = self.ipc {
+            ipc.finish()?;
+        }
+
+        Ok(())
+    }
+}
+
+/// A row group of an `OutputWriter`, whose columns are written with the `write_parquet_*`
+/// functions in the order of the schema.
+pub struct RowGroupWriter<'a, W: Write + Send> {
+    parquet: Option<SerializedRowGroupWriter<'a, W>>,
+    ipc: Option<(&'a mut IpcWriter, Vec<ArrayRef>)>,
+}
+
+impl<W: Write + Send> RowGroupWriter<'_, W> {
+    /// Adds a column to the record batch of the Arrow IPC file, if there is one.
+    fn push_array(&mut self, build: impl FnOnce() -> ArrayRef) {
+        if let Some((_, columns)) = &mut self.ipc {
+            columns.push(build());
+        }
+    }
+
+    pub fn close(self) -> Result<()> {
+        if let Some(parquet) = self.parquet {
+            parquet.close()?;
+        }
+        if let Some((ipc, columns)) = self.ipc {
+            ipc.write(columns)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Inverse of the levels written by `write_list_of_lists_column`, with `None` for null rows
+fn assemble_list_of_lists(
+    values: &[i32],
+    definition_levels: &[i16],
+    repetition_levels: &[i16],
+    null_levels: i16,
+) -> Vec<Option<Vec<Vec<i32>>>> {
+    let mut rows: Vec<Option<Vec<Vec<i32>>>> = Vec::new();
+    let mut values = values.iter();
+
+    for (&d, &r) in definition_levels.iter().zip(repetition_levels) {
+        if r == 0 {
+            rows.push((d >= null_levels).then(Vec::new));
+        }
+
+        if d <= null_levels {
+            continue;
+        }
+        let row = rows.last_mut().expect("row").as_mut().expect("row");
+
+        if r <= 1 {
+            row.push(Vec::new());
+        }
+        if d == null_levels + 2 {
+            let list = row.last_mut().expect("list");
+            list.push(*values.next().expect("value"));
+        }
+    }
+
+    rows
+}
+
+/// The number of inner lists of each row of a list of lists column read with the given levels,
+/// or `None` for null rows. `max_definition_level` is 3 for nullable columns and 2 otherwise.
+pub fn list_of_lists_lengths(
+    definition_levels: &[i16],
+    repetition_levels: &[i16],
+    max_definition_level: i16,
+) -> Vec<Option<i32>> {
+    let null_levels = max_definition_level - 2;
+    let mut lengths: Vec<Option<i32>> = Vec::new();
+
+    for (&d, &r) in definition_levels.iter().zip(repetition_levels) {
+        if r == 0 {
+            lengths.push((d >= null_levels).then_some(0));
+        }
+        if d > null_levels && r <= 1 {
+            *lengths.last_mut().expect("row").as_mut().expect("row") += 1;
+        }
+    }
+
+    lengths
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytes::Bytes;
+
+    fn round_trip(kinds: &[ColumnKind], columns: Vec<ColumnValues>) -> Vec<ColumnValues> {
+        let names: Vec<String> = (0..kinds.len()).map(|c| format!("c{}", c)).collect();
+
+        let mut buffer = Vec::new();
+        let mut writer = ParquetTableWriter::new(&mut buffer, &names, kinds, &[]).unwrap();
+        writer.write_row_group(&columns).unwrap();
+        writer.close().unwrap();
+
+        let table = ParquetTable::new(Bytes::from(buffer)).unwrap();
+        assert_eq!(table.kinds(), kinds);
+        table.read_row_group(0).unwrap()
+    }
+
+    #[test]
+    fn list_of_lists_round_trip() {
+        let rows = vec![vec![vec![1, 2], vec![3, 4]], vec![], vec![vec![5, 6]]];
+        let optional_rows = vec![Some(vec![vec![1], vec![2]]), None, Some(vec![]), None];
+
+        let columns = round_trip(
+            &[ColumnKind::IntListOfLists],
+            vec![ColumnValues::IntListOfLists(rows.clone())],
+        );
+        assert!(matches!(&columns[0], ColumnValues::IntListOfLists(x) if *x == rows));
+
+        let columns = round_trip(
+            &[ColumnKind::OptionalIntListOfLists],
+            vec![ColumnValues::OptionalIntListOfLists(optional_rows.clone())],
+        );
+        assert!(
+            matches!(&columns[0], ColumnValues::OptionalIntListOfLists(x) if *x == optional_rows)
+        );
+    }
+
+    #[test]
+    fn empty_inner_lists_round_trip() {
+        let rows = vec![vec![vec![], vec![]], vec![], vec![vec![]]];
+        let optional_rows = vec![Some(vec![vec![]]), None, Some(vec![])];
+
+        let columns = round_trip(
+            &[
+                ColumnKind::IntListOfLists,
+                ColumnKind::OptionalIntListOfLists,
+            ],
+            vec![
+                ColumnValues::IntListOfLists(rows.clone()),
+                ColumnValues::OptionalIntListOfLists(optional_rows.clone()),
+            ],
+        );
+        assert!(matches!(&columns[0], ColumnValues::IntListOfLists(x) if *x == rows));
+        assert!(
+            matches!(&columns[1], ColumnValues::OptionalIntListOfLists(x) if *x == optional_rows)
+        );
+    }
+
+    #[test]
+    fn list_of_lists_lengths_from_levels() {
+        // null, empty, two lists of two values, one empty inner list
+        let definition_levels = [0, 1, 3, 3, 3, 3, 2];
+        let repetition_levels = [0, 0, 0, 2, 1, 2, 0];
+        assert_eq!(
+            list_of_lists_lengths(&definition_levels, &repetition_levels, 3),
+            [None, Some(0), Some(2), Some(1)]
+        );
+    }
+
+    #[test]
+    fn ipc_output_has_the_parquet_columns() {
+        use arrow_array::cast::AsArray;
+        use arrow_array::types::Int32Type;
+        use arrow_array::{Array as _, RecordBatch};
+        use arrow_ipc::reader::FileReader;
+
+        let directory = std::env::temp_dir();
+        let parquet_path = directory.join(format!("{}-output.parquet", std::process::id()));
+        let ipc_path = directory.join(format!("{}-output.arrow", std::process::id()));
+
+        let fields = vec![
+            build_parquet_int_field("vertex_count").unwrap(),
+            build_parquet_optional_int_field("h11").unwrap(),
+            build_parquet_optional_int_list_of_lists_field("dual_vertices").unwrap(),
+        ];
+        let schema = SchemaType::group_type_builder("schema")
+            .with_fields(fields)
+            .build()
+            .unwrap();
+
+        let output = ParquetOutput::new(Some(parquet_path.clone()), Some(ipc_path.clone()))
+            .unwrap()
+            .unwrap();
+        let writer_props = Arc::new(WriterProperties::builder().build());
+        let mut writer = output.writer(Arc::new(schema), writer_props).unwrap();
+        writer.append_key_value_metadata(KeyValue::new("dimension".to_owned(), "2".to_owned()));
+
+        for g in 0..2 {
+            let mut row_group_writer = writer.next_row_group().unwrap();
+            write_parquet_int_column(&mut row_group_writer, &[2 + g, 0]).unwrap();
+            write_optional_parquet_int_column(&mut row_group_writer, &[Some(g), None]).unwrap();
+            write_optional_parquet_int_list_of_lists_column(
+                &mut row_group_writer,
+                &[1, 2, 3, 4],
+                &[Some(2), None],
+                2,
+            )
+            .unwrap();
+            row_group_writer.close().unwrap();
+        }
+        writer.close().unwrap();
+
+        // one record batch per row group, with the values of the Parquet file
+        let reader = FileReader::try_new(File::open(&ipc_path).unwrap(), None).unwrap();
+        assert_eq!(reader.schema().metadata()["dimension"], "2");
+        let batches: Vec<RecordBatch> = reader.collect::<Result<_, _>>().unwrap();
+        assert_eq!(batches.len(), 2);
+
+        let batch = &batches[1];
+        let vertex_counts = batch.column(0).as_primitive::<Int32Type>();
+        assert_eq!(vertex_counts.values().as_ref(), [3, 0]);
+        let h11: Vec<Option<i32>> = batch.column(1).as_primitive::<Int32Type>().iter().collect();
+        assert_eq!(h11, [Some(1), None]);
+        let dual_vertices = batch.column(2).as_list::<i32>();
+        assert!(dual_vertices.is_null(1));
+        let lists = dual_vertices.value(0);
+        let second = lists.as_list::<i32>().value(1);
+        assert_eq!(second.as_primitive::<Int32Type>().values().as_ref(), [3, 4]);
+
+        let table = ParquetTable::open(&parquet_path).unwrap();
+        let columns = table.read_row_group(1).unwrap();
+        assert!(matches!(&columns[1], ColumnValues::OptionalInt(x) if *x == h11));
+
+        std::fs::remove_file(parquet_path).unwrap();
+        std::fs::remove_file(ipc_path).unwrap();
+    }
+}
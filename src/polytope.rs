@@ -0,0 +1,1040 @@
+use std::cmp::{max, min, Reverse};
+use std::collections::BTreeMap;
+use std::iter;
+
+use anyhow::{bail, Result};
+
+/// The Euler characteristic of a Calabi-Yau threefold or fourfold with the Hodge numbers
+/// `h11, h12` or `h11, h12, h13`, or `None` for other numbers of Hodge numbers.
+pub fn euler_characteristic(hodge_numbers: &[i64]) -> Option<i64> {
+    match *hodge_numbers {
+        [h11, h12] => Some(2 * (h11 - h12)),
+        [h11, h12, h13] => Some(48 + 6 * (h11 - h12 + h13)),
+        _ => None,
+    }
+}
+
+/// A facet inequality `normal · x + offset >= 0` with a primitive normal.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Facet {
+    pub normal: Vec<i64>,
+    pub offset: i64,
+}
+
+impl Facet {
+    pub fn evaluate(&self, point: &[i64]) -> i64 {
+        self.normal
+            .iter()
+            .zip(point)
+            .map(|(a, x)| a * x)
+            .sum::<i64>()
+            + self.offset
+    }
+}
+
+/// A full-dimensional lattice polytope given by its vertices and facets.
+pub struct Polytope {
+    pub dimension: usize,
+    pub vertices: Vec<Vec<i64>>,
+    pub facets: Vec<Facet>,
+}
+
+struct HullFacet {
+    facet: Facet,
+    /// indices of the points on the facet
+    points: Vec<usize>,
+}
+
+fn gcd(a: i128, b: i128) -> i128 {
+    let (mut a, mut b) = (a.abs(), b.abs());
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a
+}
+
+fn make_primitive(v: &mut [i128]) {
+    let g = v.iter().fold(0, |g, &x| gcd(g, x));
+    if g > 1 {
+        v.iter_mut().for_each(|x| *x /= g);
+    }
+}
+
+/// Incrementally computes a row echelon form to find linearly independent vectors.
+#[derive(Default)]
+struct RowEchelon {
+    rows: Vec<(usize, Vec<i128>)>,
+}
+
+impl RowEchelon {
+    /// Adds `v` if it is linearly independent of the vectors added so far.
+    fn insert(&mut self, v: &[i64]) -> bool {
+        let mut v: Vec<i128> = v.iter().map(|&x| x.into()).collect();
+
+        // rows are reduced with respect to all earlier rows, so reducing in insertion order
+        // does not reintroduce entries at earlier pivots
+        for (pivot, row) in &self.rows {
+            if v[*pivot] != 0 {
+                let a = row[*pivot];
+                let b = v[*pivot];
+                for (x, y) in v.iter_mut().zip(row) {
+                    *x = *x * a - y * b;
+                }
+                make_primitive(&mut v);
+            }
+        }
+
+        match v.iter().position(|&x| x != 0) {
+            Some(pivot) => {
+                self.rows.push((pivot, v));
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn rank(&self) -> usize {
+        self.rows.len()
+    }
+}
+
+fn determinant(mut m: Vec<Vec<i128>>) -> i128 {
+    // Bareiss algorithm
+    let n = m.len();
+    let mut sign = 1;
+    let mut previous = 1;
+
+    for k in 0..n {
+        if m[k][k] == 0 {
+            let Some(r) = (k + 1..n).find(|&r| m[r][k] != 0) else {
+                return 0;
+            };
+            m.swap(k, r);
+            sign = -sign;
+        }
+
+        for i in k + 1..n {
+            for j in k + 1..n {
+                m[i][j] = (m[i][j] * m[k][k] - m[i][k] * m[k][j]) / previous;
+            }
+        }
+        previous = m[k][k];
+    }
+
+    // the empty matrix, which occurs for the minors in one dimension, has determinant 1
+    sign * m.last().map_or(1, |row| row[n - 1])
+}
+
+/// Returns the hyperplane through `points`, which must be affinely independent and as many as
+/// the dimension, oriented such that `inside` is on the positive side. `inside` is given with all
+/// coordinates multiplied by `scale`.
+fn hyperplane(points: &[&[i64]], inside: &[i64], scale: i64) -> Facet {
+    let dimension = points[0].len();
+
+    let differences: Vec<Vec<i128>> = points[1..]
+        .iter()
+        .map(|p| {
+            p.iter()
+                .zip(points[0])
+                .map(|(&a, &b)| (a - b).into())
+                .collect()
+        })
+        .collect();
+
+    // generalized cross product of the differences
+    let mut normal: Vec<i128> = (0..dimension)
+        .map(|j| {
+            let minor = differences
+                .iter()
+                .map(|row| (0..dimension).filter(|&k| k != j).map(|k| row[k]).collect())
+                .collect();
+            let sign = if j % 2 == 0 { 1 } else { -1 };
+            sign * determinant(minor)
+        })
+        .collect();
+    make_primitive(&mut normal);
+
+    let mut facet = Facet {
+        normal: normal.iter().map(|&x| x as i64).collect(),
+        offset: 0,
+    };
+    facet.offset = -facet.evaluate(points[0]);
+
+    let inside_value: i64 = facet
+        .normal
+        .iter()
+        .zip(inside)
+        .map(|(a, x)| a * x)
+        .sum::<i64>()
+        + facet.offset * scale;
+    if inside_value < 0 {
+        facet.normal.iter_mut().for_each(|x| *x = -*x);
+        facet.offset = -facet.offset;
+    }
+
+    facet
+}
+
+/// Selects points of `indices` spanning their affine hull.
+fn affinely_independent(points: &[Vec<i64>], indices: &[usize]) -> Vec<usize> {
+    let Some(&first) = indices.first() else {
+        return Vec::new();
+    };
+
+    let mut echelon = RowEchelon::default();
+    let mut ret = vec![first];
+
+    for &i in &indices[1..] {
+        let difference: Vec<i64> = points[i]
+            .iter()
+            .zip(&points[first])
+            .map(|(a, b)| a - b)
+            .collect();
+        if echelon.insert(&difference) {
+            ret.push(i);
+        }
+    }
+
+    ret
+}
+
+/// Subtracts `q` times row `source` from row `target`.
+fn subtract_row(m: &mut [Vec<i128>], target: usize, source: usize, q: i128) {
+    if q == 0 {
+        return;
+    }
+    let source = m[source].clone();
+    for (x, y) in m[target].iter_mut().zip(source) {
+        *x -= q * y;
+    }
+}
+
+/// Computes the Hermite normal form of `m` with row operations, such that the pivots are positive
+/// and the entries above them are reduced modulo the pivots.
+fn hermite_normal_form(m: &[Vec<i64>]) -> Vec<Vec<i64>> {
+    let mut m: Vec<Vec<i128>> = m
+        .iter()
+        .map(|row| row.iter().map(|&x| x.into()).collect())
+        .collect();
+    let rows = m.len();
+    let cols = m.first().map_or(0, |x| x.len());
+
+    let mut pivot_row = 0;
+    for col in 0..cols {
+        if pivot_row == rows {
+            break;
+        }
+
+        // Euclid's algorithm on the column, leaving the gcd in the pivot row
+        while let Some(r) = (pivot_row..rows)
+            .filter(|&r| m[r][col] != 0)
+            .min_by_key(|&r| m[r][col].abs())
+        {
+            m.swap(pivot_row, r);
+
+            for r in pivot_row + 1..rows {
+                let q = m[r][col].div_euclid(m[pivot_row][col]);
+                subtract_row(&mut m, r, pivot_row, q);
+            }
+
+            if (pivot_row + 1..rows).all(|r| m[r][col] == 0) {
+                break;
+            }
+        }
+
+        if m[pivot_row][col] == 0 {
+            continue;
+        }
+        if m[pivot_row][col] < 0 {
+            m[pivot_row].iter_mut().for_each(|x| *x = -*x);
+        }
+
+        for r in 0..pivot_row {
+            let q = m[r][col].div_euclid(m[pivot_row][col]);
+            subtract_row(&mut m, r, pivot_row, q);
+        }
+
+        pivot_row += 1;
+    }
+
+    m.iter()
+        .map(|row| row.iter().map(|&x| x as i64).collect())
+        .collect()
+}
+
+/// A partial arrangement of the vertex-facet pairing matrix in the search for its maximal form.
+#[derive(Clone)]
+struct Arrangement {
+    /// facets used for the rows so far
+    rows: Vec<usize>,
+    /// ordered blocks of vertices which can still be permuted among each other
+    blocks: Vec<Vec<usize>>,
+}
+
+impl Arrangement {
+    /// The row of `facet` with the columns of each block sorted in descending order, and the
+    /// blocks refined accordingly.
+    fn next_row(&self, pairing: &[Vec<i64>], facet: usize) -> (Vec<i64>, Vec<Vec<usize>>) {
+        let mut row = Vec::new();
+        let mut blocks = Vec::new();
+
+        for block in &self.blocks {
+            let mut block = block.clone();
+            block.sort_by_key(|&v| Reverse(pairing[facet][v]));
+
+            for chunk in block.chunk_by(|&a, &b| pairing[facet][a] == pairing[facet][b]) {
+                row.extend(iter::repeat_n(pairing[facet][chunk[0]], chunk.len()));
+                blocks.push(chunk.to_vec());
+            }
+        }
+
+        (row, blocks)
+    }
+}
+
+/// Lattice points in the relative interiors of the faces of a reflexive polytope, with each face
+/// given by the indices of the facets containing it.
+struct FaceInteriors<'a> {
+    polytope: &'a Polytope,
+    point_count: usize,
+    counts: BTreeMap<Vec<usize>, i64>,
+}
+
+impl<'a> FaceInteriors<'a> {
+    fn new(polytope: &'a Polytope) -> FaceInteriors<'a> {
+        let points = polytope.lattice_points();
+        let mut counts = BTreeMap::new();
+
+        for point in &points {
+            let facets: Vec<usize> = (0..polytope.facets.len())
+                .filter(|&f| polytope.facets[f].evaluate(point) == 0)
+                .collect();
+            // the origin is the only point not on the boundary
+            if !facets.is_empty() {
+                *counts.entry(facets).or_insert(0) += 1;
+            }
+        }
+
+        FaceInteriors {
+            polytope,
+            point_count: points.len(),
+            counts,
+        }
+    }
+
+    fn codimension(&self, face: &[usize]) -> usize {
+        let mut echelon = RowEchelon::default();
+        for &f in face {
+            echelon.insert(&self.polytope.facets[f].normal);
+        }
+        echelon.rank()
+    }
+
+    /// The face of the dual polytope dual to `face`, whose vertices are the normals of the facets
+    /// containing `face`.
+    fn dual_face(&self, face: &[usize], dual: &FaceInteriors) -> Vec<usize> {
+        (0..dual.polytope.facets.len())
+            .filter(|&g| {
+                face.iter().all(|&f| {
+                    dual.polytope.facets[g].evaluate(&self.polytope.facets[f].normal) == 0
+                })
+            })
+            .collect()
+    }
+
+    /// The sum of `l*(θ) l*(θ*)` over all faces `θ` of codimension `codimension`, where `l*`
+    /// counts the interior lattice points and `θ*` is the dual face. For facets, `l*(θ*)` is one.
+    fn sum(&self, codimension: usize, dual: &FaceInteriors) -> i64 {
+        self.counts
+            .iter()
+            .filter(|(face, _)| self.codimension(face) == codimension)
+            .map(|(face, count)| {
+                if codimension == 1 {
+                    *count
+                } else {
+                    let dual_face = self.dual_face(face, dual);
+                    count * dual.counts.get(&dual_face).copied().unwrap_or(0)
+                }
+            })
+            .sum()
+    }
+}
+
+/// Returns a matrix whose rows map the integer vectors orthogonal to `weights` bijectively onto
+/// the integer vectors with one coordinate less.
+fn orthogonal_lattice_coordinates(weights: &[i64]) -> Vec<Vec<i64>> {
+    let n = weights.len();
+
+    // column operations reduce the weights to (gcd, 0, ..., 0), and the matrix below is the
+    // inverse of their product, so that its first row is proportional to the weights
+    let mut w = weights.to_vec();
+    let mut inverse: Vec<Vec<i64>> = (0..n)
+        .map(|i| (0..n).map(|j| (i == j).into()).collect())
+        .collect();
+
+    for i in 1..n {
+        while w[i] != 0 {
+            let q = w[0].div_euclid(w[i]);
+            w[0] -= q * w[i];
+            let row = inverse[0].clone();
+            for (x, y) in inverse[i].iter_mut().zip(row) {
+                *x += q * y;
+            }
+
+            w.swap(0, i);
+            inverse.swap(0, i);
+        }
+    }
+
+    inverse.remove(0);
+    inverse
+}
+
+/// Calls `f` with each vector of non-negative integers `a` with `weights · a = degree`, where
+/// `gcds[i]` is the gcd of `weights[i..]`. A branch is dropped as soon as its remaining degree is
+/// not a multiple of the gcd of the remaining weights.
+fn for_each_exponent(
+    weights: &[i64],
+    gcds: &[i64],
+    degree: i64,
+    a: &mut Vec<i64>,
+    f: &mut impl FnMut(&[i64]),
+) {
+    let i = a.len();
+
+    if degree % gcds[i] != 0 {
+        return;
+    }
+
+    if i + 1 == weights.len() {
+        a.push(degree / weights[i]);
+        f(a);
+        a.pop();
+        return;
+    }
+
+    for x in 0..=degree / weights[i] {
+        a.push(x);
+        for_each_exponent(weights, gcds, degree - x * weights[i], a, f);
+        a.pop();
+    }
+}
+
+impl Polytope {
+    /// Computes the convex hull of `points` with an incremental algorithm.
+    pub fn new(points: &[Vec<i64>]) -> Result<Polytope> {
+        let Some(dimension) = points.first().map(|x| x.len()) else {
+            bail!("no points");
+        };
+
+        let all: Vec<usize> = (0..points.len()).collect();
+        let simplex = affinely_independent(points, &all);
+        if simplex.len() != dimension + 1 {
+            bail!("polytope is not full-dimensional");
+        }
+
+        // a multiple of the barycenter of the simplex is an interior point of all hulls
+        let scale = (dimension + 1) as i64;
+        let inside: Vec<i64> = (0..dimension)
+            .map(|k| simplex.iter().map(|&i| points[i][k]).sum())
+            .collect();
+
+        let mut facets: Vec<HullFacet> = (0..=dimension)
+            .map(|omitted| {
+                let facet_points: Vec<usize> = simplex
+                    .iter()
+                    .enumerate()
+                    .filter(|&(k, _)| k != omitted)
+                    .map(|(_, &i)| i)
+                    .collect();
+                let coordinates: Vec<&[i64]> =
+                    facet_points.iter().map(|&i| points[i].as_slice()).collect();
+
+                HullFacet {
+                    facet: hyperplane(&coordinates, &inside, scale),
+                    points: facet_points,
+                }
+            })
+            .collect();
+
+        for p in 0..points.len() {
+            if simplex.contains(&p) {
+                continue;
+            }
+
+            let visible: Vec<bool> = facets
+                .iter()
+                .map(|f| f.facet.evaluate(&points[p]) < 0)
+                .collect();
+
+            if !visible.iter().any(|&x| x) {
+                continue;
+            }
+
+            let mut new_facets: Vec<HullFacet> = Vec::new();
+
+            for (facet, _) in facets.iter().zip(&visible).filter(|(_, &v)| v) {
+                for (other, _) in facets.iter().zip(&visible).filter(|(_, &v)| !v) {
+                    let ridge: Vec<usize> = facet
+                        .points
+                        .iter()
+                        .filter(|i| other.points.contains(i))
+                        .copied()
+                        .collect();
+
+                    if ridge.len() + 1 < dimension {
+                        continue;
+                    }
+
+                    let mut spanning = affinely_independent(points, &ridge);
+                    if spanning.len() + 1 != dimension {
+                        continue;
+                    }
+
+                    spanning.push(p);
+                    let coordinates: Vec<&[i64]> =
+                        spanning.iter().map(|&i| points[i].as_slice()).collect();
+                    let new_facet = hyperplane(&coordinates, &inside, scale);
+
+                    // points coplanar with a neighboring facet extend it
+                    if new_facet == other.facet {
+                        new_facets.push(HullFacet {
+                            facet: new_facet,
+                            points: vec![p],
+                        });
+                        continue;
+                    }
+
+                    let mut facet_points = ridge;
+                    facet_points.push(p);
+                    new_facets.push(HullFacet {
+                        facet: new_facet,
+                        points: facet_points,
+                    });
+                }
+            }
+
+            let mut kept: Vec<HullFacet> = facets
+                .into_iter()
+                .zip(&visible)
+                .filter(|(_, &v)| !v)
+                .map(|(f, _)| f)
+                .collect();
+
+            for new_facet in new_facets {
+                match kept.iter_mut().find(|f| f.facet == new_facet.facet) {
+                    Some(f) => {
+                        for i in new_facet.points {
+                            if !f.points.contains(&i) {
+                                f.points.push(i);
+                            }
+                        }
+                    }
+                    None => kept.push(new_facet),
+                }
+            }
+
+            facets = kept;
+        }
+
+        // boundary points which are not vertices may have been kept on the facets
+        let mut incident: Vec<Vec<usize>> = vec![Vec::new(); points.len()];
+        for (f, facet) in facets.iter().enumerate() {
+            for &i in &facet.points {
+                incident[i].push(f);
+            }
+        }
+
+        let vertices: Vec<Vec<i64>> = incident
+            .iter()
+            .enumerate()
+            .filter(|(_, fs)| {
+                let mut echelon = RowEchelon::default();
+                for &f in fs.iter() {
+                    echelon.insert(&facets[f].facet.normal);
+                }
+                echelon.rank() == dimension
+            })
+            .map(|(i, _)| points[i].clone())
+            .collect();
+
+        Ok(Polytope {
+            dimension,
+            vertices,
+            facets: facets.into_iter().map(|f| f.facet).collect(),
+        })
+    }
+
+    /// The Newton polytope of the degree `Σ w` hypersurfaces in the weighted projective space with
+    /// the given weights of index 1. Its lattice points are the exponent vectors shifted by
+    /// `(1, ..., 1)`, in a basis of the integer vectors orthogonal to the weights.
+    pub fn from_weights(weights: &[i64]) -> Result<Polytope> {
+        let degree = weights.iter().sum();
+        let coordinates = orthogonal_lattice_coordinates(weights);
+
+        let mut gcds = weights.to_vec();
+        for i in (0..gcds.len().saturating_sub(1)).rev() {
+            gcds[i] = gcd(gcds[i].into(), gcds[i + 1].into()) as i64;
+        }
+
+        let mut points = Vec::new();
+        for_each_exponent(weights, &gcds, degree, &mut Vec::new(), &mut |a| {
+            points.push(
+                coordinates
+                    .iter()
+                    .map(|row| row.iter().zip(a).map(|(r, x)| r * (x - 1)).sum())
+                    .collect(),
+            );
+        });
+
+        Polytope::new(&points)
+    }
+
+    /// Enumerates all lattice points.
+    pub fn lattice_points(&self) -> Vec<Vec<i64>> {
+        let d = self.dimension;
+
+        let mut low = self.vertices[0].clone();
+        let mut high = self.vertices[0].clone();
+        for v in &self.vertices {
+            for k in 0..d {
+                low[k] = min(low[k], v[k]);
+                high[k] = max(high[k], v[k]);
+            }
+        }
+
+        let mut ret = Vec::new();
+        let mut point = low.clone();
+
+        // iterates over the bounding box of all coordinates but the last, whose range is
+        // obtained from the facet inequalities
+        'outer: loop {
+            let mut last_low = low[d - 1];
+            let mut last_high = high[d - 1];
+
+            for facet in &self.facets {
+                let a = facet.normal[d - 1];
+                let rest: i64 = facet.normal[..d - 1]
+                    .iter()
+                    .zip(&point)
+                    .map(|(a, x)| a * x)
+                    .sum::<i64>()
+                    + facet.offset;
+
+                // a * x + rest >= 0
+                match a.signum() {
+                    1 => last_low = max(last_low, -rest.div_euclid(a)),
+                    -1 => last_high = min(last_high, rest.div_euclid(-a)),
+                    _ if rest < 0 => last_high = last_low - 1,
+                    _ => {}
+                }
+            }
+
+            for x in last_low..=last_high {
+                point[d - 1] = x;
+                ret.push(point.clone());
+            }
+
+            for k in (0..d - 1).rev() {
+                if point[k] < high[k] {
+                    point[k] += 1;
+                    continue 'outer;
+                }
+                point[k] = low[k];
+            }
+
+            break;
+        }
+
+        ret
+    }
+
+    /// Whether the origin lies in the interior of the polytope.
+    pub fn has_interior_origin(&self) -> bool {
+        self.facets.iter().all(|f| f.offset > 0)
+    }
+
+    /// Whether the polytope is reflexive, which requires the origin to be the interior point.
+    pub fn is_reflexive(&self) -> bool {
+        self.facets.iter().all(|f| f.offset == 1)
+    }
+
+    /// The dual polytope, whose vertices are the facet normals of a reflexive polytope.
+    pub fn dual(&self) -> Result<Polytope> {
+        if !self.is_reflexive() {
+            bail!("polytope is not reflexive");
+        }
+
+        let normals: Vec<Vec<i64>> = self.facets.iter().map(|f| f.normal.clone()).collect();
+        Polytope::new(&normals)
+    }
+
+    /// The normal form of the polytope under lattice automorphisms, computed with the algorithm
+    /// of PALP.
+    ///
+    /// The vertex-facet pairing matrix is brought into its lexicographically maximal form by
+    /// permuting rows and columns. Each column order attaining the maximum, followed by a
+    /// canonical reordering, gives an order of the vertices, and the normal form is the smallest
+    /// Hermite normal form of the resulting vertex matrices, with one vertex per column.
+    pub fn normal_form(&self) -> Vec<Vec<i64>> {
+        let pairing: Vec<Vec<i64>> = self
+            .facets
+            .iter()
+            .map(|f| self.vertices.iter().map(|v| f.evaluate(v)).collect())
+            .collect();
+
+        let mut arrangements = vec![Arrangement {
+            rows: Vec::new(),
+            blocks: vec![(0..self.vertices.len()).collect()],
+        }];
+        let mut maximal_form = Vec::new();
+
+        for _ in 0..self.facets.len() {
+            let mut best_row = Vec::new();
+            let mut next = Vec::new();
+
+            for arrangement in &arrangements {
+                for facet in 0..self.facets.len() {
+                    if arrangement.rows.contains(&facet) {
+                        continue;
+                    }
+
+                    let (row, blocks) = arrangement.next_row(&pairing, facet);
+                    if row < best_row {
+                        continue;
+                    }
+                    if row > best_row {
+                        best_row = row;
+                        next.clear();
+                    }
+
+                    let mut rows = arrangement.rows.clone();
+                    rows.push(facet);
+                    next.push(Arrangement { rows, blocks });
+                }
+            }
+
+            maximal_form.push(best_row);
+            arrangements = next;
+        }
+
+        // columns are reordered by their maximum and then their sum
+        let mut canonical_order: Vec<usize> = (0..self.vertices.len()).collect();
+        let mut column_keys: Vec<(i64, i64)> = (0..self.vertices.len())
+            .map(|j| {
+                let column = maximal_form.iter().map(|row| row[j]);
+                (column.clone().max().unwrap_or(0), column.sum())
+            })
+            .collect();
+        for i in 0..column_keys.len() {
+            let mut k = i;
+            for j in i + 1..column_keys.len() {
+                if column_keys[j] < column_keys[k] {
+                    k = j;
+                }
+            }
+            column_keys.swap(i, k);
+            canonical_order.swap(i, k);
+        }
+
+        arrangements
+            .iter()
+            .map(|arrangement| {
+                let order: Vec<usize> = arrangement.blocks.concat();
+                let matrix: Vec<Vec<i64>> = (0..self.dimension)
+                    .map(|k| {
+                        canonical_order
+                            .iter()
+                            .map(|&j| self.vertices[order[j]][k])
+                            .collect()
+                    })
+                    .collect();
+                hermite_normal_form(&matrix)
+            })
+            .min()
+            .map(|matrix| {
+                (0..self.vertices.len())
+                    .map(|j| matrix.iter().map(|row| row[j]).collect())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// The Hodge numbers `h11, h12, ...` of the Calabi-Yau hypersurface whose Newton polytope is
+    /// this reflexive polytope, with Batyrev's formula. Only implemented for dimensions 4 and 5,
+    /// `None` is returned for other dimensions.
+    pub fn hodge_numbers(&self) -> Result<Option<Vec<i64>>> {
+        if !(4..=5).contains(&self.dimension) {
+            return Ok(None);
+        }
+
+        let dual = self.dual()?;
+        let n = self.dimension as i64;
+
+        let interiors = FaceInteriors::new(self);
+        let dual_interiors = FaceInteriors::new(&dual);
+
+        let h11 = dual_interiors.point_count as i64 - n - 1 - dual_interiors.sum(1, &interiors)
+            + dual_interiors.sum(2, &interiors);
+        // h12 in four dimensions and h13 in five
+        let h1n = interiors.point_count as i64 - n - 1 - interiors.sum(1, &dual_interiors)
+            + interiors.sum(2, &dual_interiors);
+
+        Ok(Some(if self.dimension == 4 {
+            vec![h11, h1n]
+        } else {
+            // pairs of dual two-dimensional faces with interior points contribute to h12
+            vec![h11, interiors.sum(3, &dual_interiors), h1n]
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn simplex(dimension: usize, scale: i64) -> Vec<Vec<i64>> {
+        let mut points = vec![vec![0; dimension]];
+        for i in 0..dimension {
+            let mut v = vec![0; dimension];
+            v[i] = scale;
+            points.push(v);
+        }
+        points
+    }
+
+    fn cross_polytope(dimension: usize) -> Vec<Vec<i64>> {
+        let mut points = Vec::new();
+        for i in 0..dimension {
+            for x in [1, -1] {
+                let mut v = vec![0; dimension];
+                v[i] = x;
+                points.push(v);
+            }
+        }
+        points
+    }
+
+    fn binomial(n: i64, k: i64) -> i64 {
+        (1..=k).fold(1, |acc, i| acc * (n - k + i) / i)
+    }
+
+    #[test]
+    fn simplex_point_counts() {
+        for dimension in 1..=4 {
+            for scale in 1..=3 {
+                let polytope = Polytope::new(&simplex(dimension, scale)).unwrap();
+                let expected = binomial(dimension as i64 + scale, dimension as i64);
+
+                assert_eq!(polytope.vertices.len(), dimension + 1);
+                assert_eq!(polytope.facets.len(), dimension + 1);
+                assert_eq!(polytope.lattice_points().len() as i64, expected);
+                assert!(!polytope.has_interior_origin());
+            }
+        }
+    }
+
+    #[test]
+    fn cross_polytope_point_counts() {
+        for dimension in 2..=5 {
+            let polytope = Polytope::new(&cross_polytope(dimension)).unwrap();
+
+            assert_eq!(polytope.vertices.len(), 2 * dimension);
+            assert_eq!(polytope.facets.len(), 1 << dimension);
+            assert_eq!(polytope.lattice_points().len(), 2 * dimension + 1);
+            assert!(polytope.is_reflexive());
+
+            // the dual is the cube [-1, 1]^n
+            let dual = polytope.dual().unwrap();
+            assert_eq!(dual.vertices.len(), 1 << dimension);
+            assert_eq!(dual.lattice_points().len(), 3usize.pow(dimension as u32));
+        }
+    }
+
+    #[test]
+    fn non_vertices_are_dropped() {
+        let mut points = cross_polytope(3);
+        points.push(vec![0, 0, 0]);
+        points.push(vec![1, 0, 0]);
+
+        let polytope = Polytope::new(&points).unwrap();
+        assert_eq!(polytope.vertices.len(), 6);
+    }
+
+    /// Applies `matrix` to each point and reverses their order.
+    fn transform(points: &[Vec<i64>], matrix: &[Vec<i64>]) -> Vec<Vec<i64>> {
+        points
+            .iter()
+            .rev()
+            .map(|p| {
+                matrix
+                    .iter()
+                    .map(|row| row.iter().zip(p).map(|(a, x)| a * x).sum())
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn normal_form_is_invariant() {
+        // unimodular matrices, a product of elementary ones and a signed permutation
+        let matrices = [
+            vec![
+                vec![1, 2, 0, -1],
+                vec![0, 1, 3, 0],
+                vec![0, 0, 1, 5],
+                vec![0, 0, 0, 1],
+            ],
+            vec![
+                vec![0, -1, 0, 0],
+                vec![0, 0, 0, 1],
+                vec![1, 0, 0, 0],
+                vec![0, 0, -1, 0],
+            ],
+        ];
+        let polytopes = [
+            simplex(4, 2),
+            cross_polytope(4),
+            vec![
+                vec![1, 0, 0, 0],
+                vec![0, 1, 0, 0],
+                vec![0, 0, 1, 0],
+                vec![0, 0, 0, 1],
+                vec![-1, -1, -1, -1],
+                vec![-1, 2, 0, 1],
+            ],
+        ];
+
+        for points in &polytopes {
+            let normal_form = Polytope::new(points).unwrap().normal_form();
+
+            for matrix in &matrices {
+                let transformed = transform(points, matrix);
+                assert_ne!(&transformed, points);
+                assert_eq!(
+                    Polytope::new(&transformed).unwrap().normal_form(),
+                    normal_form
+                );
+            }
+
+            // the normal form is a polytope in normal form
+            assert_eq!(
+                Polytope::new(&normal_form).unwrap().normal_form(),
+                normal_form
+            );
+        }
+    }
+
+    #[test]
+    fn known_normal_forms() {
+        // as computed by Sage with `lattice_polytope.cross_polytope(3).normal_form()`
+        let normal_form = Polytope::new(&cross_polytope(3)).unwrap().normal_form();
+        assert_eq!(
+            normal_form,
+            [
+                [1, 0, 0],
+                [0, 1, 0],
+                [0, 0, 1],
+                [0, 0, -1],
+                [0, -1, 0],
+                [-1, 0, 0],
+            ]
+        );
+
+        // as computed by PALP with `poly.x -N` for the mirror quintic, `M:6 5 N:126 5`
+        let points = [
+            vec![-1, -1, -1, -1],
+            vec![0, 0, 1, 0],
+            vec![1, 0, 0, 0],
+            vec![0, 0, 0, 1],
+            vec![0, 1, 0, 0],
+        ];
+        let normal_form = Polytope::new(&points).unwrap().normal_form();
+        assert_eq!(
+            normal_form,
+            [
+                [1, 0, 0, 0],
+                [0, 1, 0, 0],
+                [0, 0, 1, 0],
+                [0, 0, 0, 1],
+                [-1, -1, -1, -1],
+            ]
+        );
+    }
+
+    #[test]
+    fn normal_form_distinguishes_polytopes() {
+        let cross_polytope = Polytope::new(&cross_polytope(3)).unwrap();
+        let cube = cross_polytope.dual().unwrap();
+        let simplex = Polytope::new(&simplex(3, 2)).unwrap();
+
+        let normal_forms = [
+            cross_polytope.normal_form(),
+            cube.normal_form(),
+            simplex.normal_form(),
+        ];
+        for i in 0..normal_forms.len() {
+            for j in 0..i {
+                assert_ne!(normal_forms[i], normal_forms[j]);
+            }
+        }
+    }
+
+    #[test]
+    fn quintic_hodge_numbers() {
+        let quintic = Polytope::from_weights(&[1, 1, 1, 1, 1]).unwrap();
+        assert_eq!(quintic.lattice_points().len(), 126);
+
+        let hodge_numbers = quintic.hodge_numbers().unwrap().unwrap();
+        assert_eq!(hodge_numbers, [1, 101]);
+        assert_eq!(euler_characteristic(&hodge_numbers), Some(-200));
+
+        // the mirror quintic exchanges h11 and h12
+        let mirror = quintic.dual().unwrap();
+        assert_eq!(mirror.hodge_numbers().unwrap().unwrap(), [101, 1]);
+    }
+
+    #[test]
+    fn weighted_exponents() {
+        let weights = [1, 1, 2, 2, 2];
+        let mut count = 0;
+        for_each_exponent(&weights, &[1, 1, 2, 2, 2], 8, &mut Vec::new(), &mut |a| {
+            assert_eq!(a.iter().zip(&weights).map(|(x, w)| x * w).sum::<i64>(), 8);
+            count += 1;
+        });
+        assert_eq!(count, 105);
+
+        // the remaining degree 3 is never a multiple of the remaining weights
+        for_each_exponent(&[2, 4, 6], &[2, 2, 6], 3, &mut Vec::new(), &mut |_| {
+            panic!("unexpected exponent")
+        });
+
+        let octic = Polytope::from_weights(&weights).unwrap();
+        assert_eq!(octic.hodge_numbers().unwrap().unwrap(), [2, 86]);
+    }
+
+    #[test]
+    fn sextic_hodge_numbers() {
+        let sextic = Polytope::from_weights(&[1, 1, 1, 1, 1, 1]).unwrap();
+
+        let hodge_numbers = sextic.hodge_numbers().unwrap().unwrap();
+        assert_eq!(hodge_numbers, [1, 0, 426]);
+        assert_eq!(euler_characteristic(&hodge_numbers), Some(2610));
+    }
+
+    #[test]
+    fn unsupported_hodge_numbers() {
+        let quartic = Polytope::from_weights(&[1, 1, 1, 1]).unwrap();
+        assert!(quartic.hodge_numbers().unwrap().is_none());
+        assert_eq!(euler_characteristic(&[1]), None);
+
+        let simplex = Polytope::new(&simplex(4, 1)).unwrap();
+        assert!(simplex.hodge_numbers().is_err());
+    }
+
+    #[test]
+    fn lower_dimensional_points_are_rejected() {
+        assert!(Polytope::new(&[]).is_err());
+        assert!(Polytope::new(&[vec![0, 0], vec![1, 1], vec![2, 2]]).is_err());
+    }
+}